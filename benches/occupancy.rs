@@ -0,0 +1,73 @@
+//! Run with `cargo bench --features bench`
+//!
+//! Measures the game on a 1000x1000 board with a long snake against the code that tracking
+//! the points each body covers and the free-cell index replaced, which is benchmarked as
+//! `replaced`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rusty_game::bench::{LongSnake, LongSnakeGame};
+
+const SIZE: i32 = 1000;
+const LENGTH: usize = 100_000;
+
+fn self_collision(c: &mut Criterion) {
+    let mut group = c.benchmark_group("self_collision");
+    let snake = LongSnake::new(SIZE, SIZE, LENGTH);
+    group.bench_function("replaced", |b| {
+        b.iter(|| black_box(&snake).is_collide_with_self_replaced())
+    });
+    group.bench_function("occupancy", |b| {
+        b.iter(|| black_box(&snake).is_collide_with_self())
+    });
+    group.finish();
+}
+
+fn free_cell_check(c: &mut Criterion) {
+    let mut group = c.benchmark_group("free_cell_check");
+    let game = LongSnakeGame::new(SIZE, SIZE, LENGTH);
+    let tail = game.tail();
+    group.bench_function("replaced", |b| {
+        b.iter(|| black_box(&game).is_free_replaced(black_box(&tail)))
+    });
+    group.bench_function("occupancy", |b| {
+        b.iter(|| black_box(&game).is_free(black_box(&tail)))
+    });
+    group.finish();
+}
+
+fn place_food(c: &mut Criterion) {
+    let mut group = c.benchmark_group("place_food");
+    let mut game = LongSnakeGame::new(SIZE, SIZE, LENGTH);
+    group.sample_size(10);
+    group.bench_function("replaced", |b| {
+        b.iter(|| game.place_food_replaced().unwrap())
+    });
+    group.sample_size(100);
+    group.bench_function("free_cell_index", |b| b.iter(|| game.place_food().unwrap()));
+    group.finish();
+}
+
+fn move_snake(c: &mut Criterion) {
+    let mut snake = LongSnake::new(SIZE, SIZE, LENGTH);
+    c.bench_function("move_snake", |b| {
+        b.iter(|| {
+            snake.advance();
+            snake.is_collide_with_self()
+        })
+    });
+}
+
+fn tick(c: &mut Criterion) {
+    let mut game = LongSnakeGame::new(SIZE, SIZE, LENGTH);
+    c.bench_function("tick", |b| b.iter(|| assert!(game.tick())));
+}
+
+criterion_group!(
+    benches,
+    self_collision,
+    free_cell_check,
+    place_food,
+    move_snake,
+    tick
+);
+criterion_main!(benches);
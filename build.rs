@@ -2,7 +2,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_build::configure()
         .type_attribute("rusty.GameState", "#[derive(serde::Serialize)]")
         .type_attribute("rusty.Point", "#[derive(serde::Serialize)]")
+        .type_attribute("rusty.Food", "#[derive(serde::Serialize)]")
+        .type_attribute("rusty.SpeedEffect", "#[derive(serde::Serialize)]")
+        .type_attribute("rusty.Snake", "#[derive(serde::Serialize)]")
+        .type_attribute("rusty.ErrorDetails", "#[derive(serde::Serialize)]")
+        .type_attribute("rusty.SeriesScore", "#[derive(serde::Serialize)]")
+        .type_attribute("rusty.Player", "#[derive(serde::Serialize)]")
+        .type_attribute("rusty.PlayerProfile", "#[derive(serde::Serialize)]")
+        .type_attribute("rusty.CreateReply", "#[derive(serde::Serialize)]")
         .type_attribute("rusty.JoinReply", "#[derive(serde::Serialize)]")
+        .type_attribute("rusty.ListGamesReply", "#[derive(serde::Serialize)]")
+        .type_attribute("rusty.GameSummary", "#[derive(serde::Serialize)]")
         .compile(&["proto/rusty.proto"], &["proto/"])?;
     Ok(())
 }
@@ -1,21 +1,18 @@
 use std::io::stdin;
-use std::time::Duration;
 
-use rusty::rusty_client::RustyClient;
-use rusty::{
-    CreateRequest, GameState as ProtoGameState, GameStatusRequest, JoinRequest,
-    Point as ProtoPoint, StartRequest, UpdateRequest,
-};
 use rusty_game::output::print_world;
-use rusty_game::proto::MoveDirection;
-use rusty_game::{GameState, Point};
+use rusty_game::proto::play_request::Action;
+use rusty_game::proto::rusty_client::RustyClient;
+use rusty_game::proto::{
+    BoardMode, CreateRequest, EmptyGamePolicy, GameMode, LeaveRequest, MoveDirection, PlayReply,
+    PlayRequest, PlaySession, PlayerProfile, StartRequest,
+};
+use rusty_game::{GameState, AUTHORIZATION};
+use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
-use tokio::time;
-use tonic::Status;
-
-pub mod rusty {
-    tonic::include_proto!("rusty");
-}
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Channel;
+use tonic::Streaming;
 
 const WORLD_SIZE: i32 = 10;
 
@@ -26,59 +23,66 @@ enum UserInputOption {
     Retry,
 }
 
-impl From<ProtoGameState> for GameState {
-    fn from(game_state: ProtoGameState) -> Self {
-        Self {
-            height: WORLD_SIZE,
-            width: WORLD_SIZE,
-            tick: 1000,
-            game_over_reason: None,
-            direction: game_state.move_direction.into(),
-            num_users: game_state.number_of_players,
-            body: game_state.body.into_iter().map(|p| p.into()).collect(),
-            food: game_state.food.unwrap().into(),
-        }
-    }
-}
-
-impl From<ProtoPoint> for Point {
-    fn from(value: ProtoPoint) -> Self {
-        Self {
-            x: value.x,
-            y: value.y,
-        }
-    }
-}
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = RustyClient::connect("http://[::1]:50051").await?;
     println!("Creating Game");
-    let game_id = create_game().await;
-    println!("Joining Game");
-    let user_id = join_game(game_id.clone()).await;
+    // The creator joins the game as the host
+    let (game_id, session_token) = create_game(&mut client).await;
     println!("Starting Game");
-    start_game(game_id.clone(), user_id.clone()).await;
-    println!("Spawning Tick");
-    let _tick_handle = spawn_ticker(game_id.clone(), user_id.clone());
+    start_game(&mut client, game_id.clone(), &session_token).await;
+    println!("Playing Game");
+    let (vote_sender, vote_receiver) = mpsc::channel(8);
+    vote_sender
+        .send(PlayRequest {
+            action: Some(Action::Session(PlaySession {
+                game_id: game_id.clone(),
+            })),
+        })
+        .await?;
+    let game_states = client
+        .play(authorized(
+            ReceiverStream::new(vote_receiver),
+            &session_token,
+        ))
+        .await?
+        .into_inner();
+    let _game_state_handle = spawn_game_state_printer(game_states);
 
     while let Ok(direction) = get_user_direction() {
-        let result = update_game(game_id.clone(), user_id.clone(), direction).await;
-        print_world(&result.unwrap().into());
+        let vote = PlayRequest {
+            action: Some(Action::MoveDirection(direction.into())),
+        };
+        if vote_sender.send(vote).await.is_err() {
+            println!("Play stream closed");
+            break;
+        }
     }
 
+    println!("Leaving Game");
+    leave_game(&mut client, game_id, &session_token).await;
     Ok(())
 }
 
-fn spawn_ticker(game_id: String, user_id: String) -> JoinHandle<()> {
+fn spawn_game_state_printer(mut game_states: Streaming<PlayReply>) -> JoinHandle<()> {
     tokio::spawn(async move {
-        let mut interval = time::interval(Duration::from_millis(100));
-
         loop {
-            interval.tick().await;
-            match game_status(game_id.clone(), user_id.clone()).await {
-                Ok(game_state) => print_world(&game_state.into()),
+            match game_states.message().await {
+                Ok(Some(reply)) => match reply.game_state.map(GameState::try_from) {
+                    Some(Ok(game_state)) => print_world(&game_state),
+                    Some(Err(err)) => println!("Skipping a game state: {}", err),
+                    None if reply.vote_retry_after_millis > 0 => println!(
+                        "Voting too fast, try again in {}ms",
+                        reply.vote_retry_after_millis
+                    ),
+                    None => println!("Skipping a reply without a game state"),
+                },
+                Ok(None) => {
+                    println!("Game over");
+                    break;
+                }
                 Err(err) => {
-                    println!("Tick exiting due to error: {}", err);
+                    println!("Play exiting due to error: {}", err);
                     break;
                 }
             }
@@ -104,84 +108,70 @@ fn get_user_direction() -> Result<MoveDirection, ()> {
         };
         println!("Input: {:?}", user_input_option);
     }
-    let direction_result = match user_input_option {
+    match user_input_option {
         UserInputOption::Direction(direction) => Ok(direction),
         _ => Err(()),
-    };
-    direction_result
+    }
 }
 
-async fn create_game() -> String {
-    let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
+/// The first argument, or the user's login name
+fn display_name() -> String {
+    std::env::args()
+        .nth(1)
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_else(|| "Player".to_string())
+}
+
+async fn create_game(client: &mut RustyClient<Channel>) -> (String, String) {
     let request = tonic::Request::new(CreateRequest {
         height: WORLD_SIZE.try_into().unwrap(),
         width: WORLD_SIZE.try_into().unwrap(),
         tick_duration_millis: 500,
+        empty_game_policy: EmptyGamePolicy::EndGame.into(),
+        board_mode: BoardMode::Bounded.into(),
+        game_mode: GameMode::CrowdVote.into(),
+        team_count: 0,
+        level: String::new(),
+        afk_timeout_millis: 0,
+        resume_countdown_millis: 3000,
+        start_countdown_millis: 3000,
+        food_count: 1,
+        special_food_percent: 0,
+        special_food_lifetime_ticks: 0,
+        seed: None,
+        host_profile: Some(PlayerProfile {
+            display_name: display_name(),
+            ..PlayerProfile::default()
+        }),
     });
 
     let response = client.create(request).await.unwrap();
 
     println!("RESPONSE={:?}", response);
-    response.into_inner().game_id
+    let reply = response.into_inner();
+    (reply.game_id, reply.session_token)
 }
 
-async fn join_game(game_id: String) -> String {
-    let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
+/// Attaches the session token that identifies the user to the request
+fn authorized<T>(message: T, session_token: &str) -> tonic::Request<T> {
+    let mut request = tonic::Request::new(message);
+    let value = format!("Bearer {}", session_token).parse().unwrap();
+    request.metadata_mut().insert(AUTHORIZATION, value);
+    request
+}
 
-    let request = tonic::Request::new(JoinRequest { game_id });
+async fn leave_game(client: &mut RustyClient<Channel>, game_id: String, session_token: &str) {
+    let request = authorized(LeaveRequest { game_id }, session_token);
 
-    let response = client.join(request).await.unwrap();
+    let response = client.leave(request).await;
 
     println!("RESPONSE={:?}", response);
-    response.into_inner().user_id
 }
 
-async fn start_game(game_id: String, user_id: String) {
-    let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
-
-    let request = tonic::Request::new(StartRequest { game_id, user_id });
+async fn start_game(client: &mut RustyClient<Channel>, game_id: String, session_token: &str) {
+    let request = authorized(StartRequest { game_id }, session_token);
 
     let response = client.start(request).await.unwrap();
 
     println!("RESPONSE={:?}", response);
 }
-
-async fn update_game(
-    game_id: String,
-    user_id: String,
-    direction: MoveDirection,
-) -> Result<ProtoGameState, Status> {
-    let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
-
-    let request = tonic::Request::new(UpdateRequest {
-        game_id,
-        user_id,
-        move_direction: direction.into(),
-    });
-
-    match client.update(request).await {
-        Ok(update_reply) => {
-            return Ok(update_reply.into_inner().game_state.unwrap());
-        }
-        Err(err) => {
-            println!("Error: {:?}", err);
-            return Err(err);
-        }
-    }
-}
-
-async fn game_status(game_id: String, user_id: String) -> Result<ProtoGameState, Status> {
-    let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
-
-    let request = tonic::Request::new(GameStatusRequest { game_id, user_id });
-
-    match client.game_status(request).await {
-        Ok(game_status_reply) => {
-            return Ok(game_status_reply.into_inner().game_state.unwrap());
-        }
-        Err(err) => {
-            println!("Error: {:?}", err);
-            return Err(err);
-        }
-    }
-}
@@ -6,11 +6,12 @@ use tonic::transport::Server;
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let addr = "[::1]:50051".parse().unwrap();
     let rusty = RustyService::new();
+    let session_interceptor = rusty.session_interceptor();
 
     println!("RustyServer listening on {}", addr);
 
     Server::builder()
-        .add_service(RustyServer::new(rusty))
+        .add_service(RustyServer::with_interceptor(rusty, session_interceptor))
         .serve(addr)
         .await?;
 
@@ -3,21 +3,52 @@
 extern crate rocket;
 use std::time::Duration;
 
+use prost::Message;
 use rocket::fairing::{Fairing, Info, Kind};
-use rocket::http::Header;
+use rocket::http::{Header, Status as HttpStatus};
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest};
+use rocket::response::stream::{Event, EventStream};
 use rocket::{Request, Response};
-use rusty::rusty_client::RustyClient;
-use rusty::{
-    CreateRequest, GameState as ProtoGameState, GameStatusRequest, JoinRequest, StartRequest,
-    UpdateRequest,
+use rusty_game::proto::rusty_client::RustyClient;
+use rusty_game::proto::{
+    BoardMode, ConfigureRequest, CreateRequest, EmptyGamePolicy, ErrorDetails, ErrorReason,
+    GameFilter, GameMode, GameState as ProtoGameState, GameStatusRequest, JoinMode, JoinRequest,
+    KickRequest, LeaveRequest, ListGamesReply, ListGamesRequest, MoveDirection, PauseRequest,
+    PlayerProfile, RestartRequest, ResumeRequest, StartRequest, TransferHostRequest, UpdateRequest,
+    WatchGameRequest,
 };
-use rusty_game::proto::MoveDirection;
+use rusty_game::{is_well_formed_token, parse_bearer_token, AUTHORIZATION};
 use serde_json::json;
 use tokio::time::sleep;
-use tonic::Status;
+use tonic::{Code, Status};
 
-pub mod rusty {
-    tonic::include_proto!("rusty");
+/// A JSON error body, sent with the HTTP status that matches the gRPC status code
+type ErrorResponse = (HttpStatus, String);
+type ApiResult = Result<String, ErrorResponse>;
+
+fn error_response(status: &Status) -> ErrorResponse {
+    let http_status = match status.code() {
+        Code::InvalidArgument | Code::OutOfRange => HttpStatus::BadRequest,
+        Code::Unauthenticated => HttpStatus::Unauthorized,
+        Code::PermissionDenied => HttpStatus::Forbidden,
+        Code::NotFound => HttpStatus::NotFound,
+        Code::AlreadyExists | Code::Aborted | Code::FailedPrecondition => HttpStatus::Conflict,
+        Code::ResourceExhausted => HttpStatus::TooManyRequests,
+        Code::Unavailable => HttpStatus::ServiceUnavailable,
+        Code::DeadlineExceeded => HttpStatus::GatewayTimeout,
+        Code::Unimplemented => HttpStatus::NotImplemented,
+        _ => HttpStatus::InternalServerError,
+    };
+    let details = ErrorDetails::decode(status.details()).unwrap_or_default();
+    let reason = ErrorReason::from_i32(details.reason).unwrap_or(ErrorReason::UnknownError);
+    let body = json!({
+        "error": true,
+        "response": status.message(),
+        "reason": format!("{:?}", reason),
+        "details": details
+    });
+    (http_status, body.to_string())
 }
 
 pub struct CORS;
@@ -42,6 +73,50 @@ impl Fairing for CORS {
     }
 }
 
+/// The session token returned by `create` or `join`, sent as `Authorization: Bearer <token>`
+/// or as the `token` query parameter
+struct SessionToken(String);
+
+impl SessionToken {
+    /// Forwards the session token to the game server
+    fn authorize<T>(&self, message: T) -> tonic::Request<T> {
+        let mut request = tonic::Request::new(message);
+        let value = format!("Bearer {}", self.0).parse().unwrap();
+        request.metadata_mut().insert(AUTHORIZATION, value);
+        request
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SessionToken {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let header = request.headers().get_one(AUTHORIZATION);
+        let token = match header {
+            Some(header) => parse_bearer_token(header).map(str::to_string),
+            None => request
+                .query_value::<String>("token")
+                .and_then(Result::ok)
+                .filter(|token| is_well_formed_token(token)),
+        };
+        match token {
+            Some(token) => Outcome::Success(SessionToken(token)),
+            None => Outcome::Error((HttpStatus::Unauthorized, ())),
+        }
+    }
+}
+
+#[catch(401)]
+fn unauthorized() -> String {
+    json!({
+        "error": true,
+        "response": "A valid session token is required. Join the game first.",
+        "reason": format!("{:?}", ErrorReason::Unauthenticated),
+    })
+    .to_string()
+}
+
 #[get("/")]
 fn index() -> &'static str {
     "Hello, world!"
@@ -53,27 +128,236 @@ async fn delay(seconds: u64) -> String {
     format!("Waited for {} seconds", seconds)
 }
 
-#[get("/create/<height>/<width>/<tick>")]
-async fn create(height: u32, width: u32, tick: u32) -> String {
-    create_game(height, width, tick).await
+/// `on_empty=pause` pauses the game when the last player leaves, otherwise the game ends.
+/// `edges=wrap` brings rusty back in on the opposite edge instead of ending the game.
+/// `game_mode=arena` gives every player their own snake instead of voting on one, and
+/// `game_mode=teams` gives each of `teams` teams (2 by default) a snake to vote on.
+/// `level` names a level map on the server to lay the board out from, which sets the size.
+/// `start_countdown` is the milliseconds of countdown between starting and the first tick,
+/// 3 seconds by default.
+/// `resume_countdown` is the milliseconds of countdown before a paused game ticks again.
+/// `food` is how many food items are on the board at once, `special_food` the percent chance
+/// that new food is a bonus, shrink or speed food, and `food_lifetime` how many ticks special
+/// food lasts before it vanishes.
+/// `seed` replays a game's random choices, such as where food appears, from the seed shown in
+/// a previous game's state.
+/// The host's profile is given like `join`'s.
+/// Responds with the game id, the host's user id and the host's session token.
+#[get(
+    "/create/<height>/<width>/<tick>?<on_empty>&<edges>&<game_mode>&<teams>&<level>&<start_countdown>&<resume_countdown>&<food>&<special_food>&<food_lifetime>&<seed>&<name>&<color>&<avatar>"
+)]
+#[allow(clippy::too_many_arguments)]
+async fn create(
+    height: u32,
+    width: u32,
+    tick: u32,
+    on_empty: Option<&str>,
+    edges: Option<&str>,
+    game_mode: Option<&str>,
+    teams: Option<u32>,
+    level: Option<&str>,
+    start_countdown: Option<u32>,
+    resume_countdown: Option<u32>,
+    food: Option<u32>,
+    special_food: Option<u32>,
+    food_lifetime: Option<u32>,
+    seed: Option<u64>,
+    name: Option<&str>,
+    color: Option<&str>,
+    avatar: Option<&str>,
+) -> ApiResult {
+    create_game(CreateRequest {
+        height,
+        width,
+        tick_duration_millis: tick,
+        empty_game_policy: to_empty_game_policy(on_empty).into(),
+        board_mode: to_board_mode(edges).into(),
+        game_mode: to_game_mode(game_mode).into(),
+        team_count: teams.unwrap_or(0),
+        level: level.unwrap_or_default().to_string(),
+        afk_timeout_millis: 0,
+        resume_countdown_millis: resume_countdown.unwrap_or(0),
+        start_countdown_millis: start_countdown.unwrap_or(0),
+        food_count: food.unwrap_or(0),
+        special_food_percent: special_food.unwrap_or(0),
+        special_food_lifetime_ticks: food_lifetime.unwrap_or(0),
+        seed,
+        host_profile: Some(to_player_profile(name, color, avatar)),
+    })
+    .await
+}
+
+/// Replaces the settings of a game in the lobby, taking the same settings as `create`
+#[get(
+    "/configure/<game_id>/<height>/<width>/<tick>?<on_empty>&<edges>&<game_mode>&<teams>&<level>&<start_countdown>&<resume_countdown>&<food>&<special_food>&<food_lifetime>&<seed>"
+)]
+#[allow(clippy::too_many_arguments)]
+async fn configure(
+    game_id: &str,
+    session: SessionToken,
+    height: u32,
+    width: u32,
+    tick: u32,
+    on_empty: Option<&str>,
+    edges: Option<&str>,
+    game_mode: Option<&str>,
+    teams: Option<u32>,
+    level: Option<&str>,
+    start_countdown: Option<u32>,
+    resume_countdown: Option<u32>,
+    food: Option<u32>,
+    special_food: Option<u32>,
+    food_lifetime: Option<u32>,
+    seed: Option<u64>,
+) -> ApiResult {
+    let request = ConfigureRequest {
+        game_id: game_id.to_string(),
+        height,
+        width,
+        tick_duration_millis: tick,
+        empty_game_policy: to_empty_game_policy(on_empty).into(),
+        board_mode: to_board_mode(edges).into(),
+        game_mode: to_game_mode(game_mode).into(),
+        team_count: teams.unwrap_or(0),
+        level: level.unwrap_or_default().to_string(),
+        afk_timeout_millis: 0,
+        resume_countdown_millis: resume_countdown.unwrap_or(0),
+        start_countdown_millis: start_countdown.unwrap_or(0),
+        food_count: food.unwrap_or(0),
+        special_food_percent: special_food.unwrap_or(0),
+        special_food_lifetime_ticks: food_lifetime.unwrap_or(0),
+        seed,
+    };
+    configure_game(request, session)
+        .await
+        .map(|_| "Done".to_owned())
+        .map_err(|err| error_response(&err))
+}
+
+#[get("/kick/<game_id>/<player_id>")]
+async fn kick(game_id: &str, session: SessionToken, player_id: &str) -> ApiResult {
+    kick_player(game_id.to_string(), session, player_id.to_string())
+        .await
+        .map(|_| "Done".to_owned())
+        .map_err(|err| error_response(&err))
+}
+
+#[get("/transfer_host/<game_id>/<new_host_id>")]
+async fn transfer_host(game_id: &str, session: SessionToken, new_host_id: &str) -> ApiResult {
+    transfer_host_role(game_id.to_string(), session, new_host_id.to_string())
+        .await
+        .map(|_| "Done".to_owned())
+        .map_err(|err| error_response(&err))
+}
+
+fn to_empty_game_policy(on_empty: Option<&str>) -> EmptyGamePolicy {
+    match on_empty {
+        Some("pause") => EmptyGamePolicy::PauseGame,
+        _ => EmptyGamePolicy::EndGame,
+    }
+}
+
+fn to_board_mode(edges: Option<&str>) -> BoardMode {
+    match edges {
+        Some("wrap") => BoardMode::WrapAround,
+        _ => BoardMode::Bounded,
+    }
+}
+
+fn to_game_mode(game_mode: Option<&str>) -> GameMode {
+    match game_mode {
+        Some("arena") => GameMode::Arena,
+        Some("teams") => GameMode::Teams,
+        _ => GameMode::CrowdVote,
+    }
+}
+
+/// The player's `name`, with an optional `color` (`#rrggbb`) and `avatar`
+fn to_player_profile(
+    name: Option<&str>,
+    color: Option<&str>,
+    avatar: Option<&str>,
+) -> PlayerProfile {
+    PlayerProfile {
+        display_name: name.unwrap_or_default().to_string(),
+        color: color.unwrap_or_default().to_string(),
+        avatar: avatar.unwrap_or_default().to_string(),
+    }
+}
+
+/// Responds with the user id and the session token that authenticates the other calls.
+/// `mode=spectate` joins as a spectator, who can watch but not vote.
+/// `team` picks the team to play for in team mode, the team with the fewest players otherwise.
+#[get("/join/<game_id>?<name>&<color>&<avatar>&<mode>&<team>")]
+async fn join(
+    game_id: &str,
+    name: Option<&str>,
+    color: Option<&str>,
+    avatar: Option<&str>,
+    mode: Option<&str>,
+    team: Option<u32>,
+) -> ApiResult {
+    let mode = match mode {
+        Some("spectate") => JoinMode::JoinAsSpectator,
+        _ => JoinMode::JoinAsPlayer,
+    };
+    join_game(
+        game_id.to_string(),
+        to_player_profile(name, color, avatar),
+        mode,
+        team.unwrap_or(0),
+    )
+    .await
+}
+
+#[get("/leave/<game_id>")]
+async fn leave(game_id: &str, session: SessionToken) -> ApiResult {
+    leave_game(game_id.to_string(), session)
+        .await
+        .map(|_| "Done".to_owned())
+        .map_err(|err| error_response(&err))
+}
+
+#[get("/start/<game_id>")]
+async fn start(game_id: &str, session: SessionToken) -> ApiResult {
+    start_game(game_id.to_string(), session)
+        .await
+        .map(|_| "Done".to_owned())
+        .map_err(|err| error_response(&err))
+}
+
+#[get("/restart/<game_id>")]
+async fn restart(game_id: &str, session: SessionToken) -> ApiResult {
+    restart_game(game_id.to_string(), session)
+        .await
+        .map(|_| "Done".to_owned())
+        .map_err(|err| error_response(&err))
 }
 
-#[get("/join/<game_id>")]
-async fn join(game_id: &str) -> String {
-    join_game(game_id.to_string()).await
+#[get("/pause/<game_id>")]
+async fn pause(game_id: &str, session: SessionToken) -> ApiResult {
+    pause_game(game_id.to_string(), session)
+        .await
+        .map(|_| "Done".to_owned())
+        .map_err(|err| error_response(&err))
 }
 
-#[get("/start/<game_id>/<user_id>")]
-async fn start(game_id: &str, user_id: &str) -> String {
-    start_game(game_id.to_string(), user_id.to_string()).await;
-    "Done".to_owned()
+#[get("/resume/<game_id>")]
+async fn resume(game_id: &str, session: SessionToken) -> ApiResult {
+    resume_game(game_id.to_string(), session)
+        .await
+        .map(|_| "Done".to_owned())
+        .map_err(|err| error_response(&err))
 }
 
-#[get("/update/<game_id>/<user_id>/<direction>")]
-async fn update(game_id: &str, user_id: &str, direction: u32) -> String {
+#[get("/update/<game_id>/<direction>")]
+async fn update(game_id: &str, session: SessionToken, direction: u32) -> ApiResult {
     if direction > 3 {
-        return "Direction should be a number from 0 to 3.\n0=North, 1=East, 2=South, 3=West"
-            .to_string();
+        return Err((
+            HttpStatus::BadRequest,
+            "Direction should be a number from 0 to 3.\n0=North, 1=East, 2=South, 3=West"
+                .to_string(),
+        ));
     }
 
     let selected_direction = match direction {
@@ -83,118 +367,292 @@ async fn update(game_id: &str, user_id: &str, direction: u32) -> String {
         _ => MoveDirection::West,
     };
 
-    let game_state_response =
-        update_game(game_id.to_string(), user_id.to_string(), selected_direction).await;
-    let response = format!("{:?}", game_state_response);
+    let game_state = update_game(game_id.to_string(), session, selected_direction)
+        .await
+        .map_err(|err| error_response(&err))?;
     let json_response = json!({
-        "error": game_state_response.is_err(),
-        "response": response
+        "error": false,
+        "response": format!("{:?}", game_state)
     });
-    json_response.to_string()
+    Ok(json_response.to_string())
 }
 
-#[get("/status/<game_id>/<user_id>")]
-async fn status(game_id: &str, user_id: &str) -> String {
-    let game_state_response = game_status(game_id.to_string(), user_id.to_string()).await;
+#[get("/status/<game_id>")]
+async fn status(game_id: &str, session: SessionToken) -> ApiResult {
+    let game_state_response = game_status(game_id.to_string(), session).await;
     match game_state_response {
-        Ok(game_state) => json!({
+        Ok(game_state) => Ok(json!({
             "error": false,
             "response": game_state
         })
-        .to_string(),
-        Err(err) => json!({
-            "error": true,
-            "response": err.to_string()
+        .to_string()),
+        Err(err) => Err(error_response(&err)),
+    }
+}
+
+/// Lists games, oldest first. `filter` is `joinable` or `running`, any other value lists all
+/// games. Pass the returned `next_page_token` as `page_token` to get the next page.
+#[get("/games?<filter>&<page_size>&<page_token>")]
+async fn games(
+    filter: Option<&str>,
+    page_size: Option<u32>,
+    page_token: Option<&str>,
+) -> ApiResult {
+    let filter = match filter {
+        Some("joinable") => GameFilter::JoinableGames,
+        Some("running") => GameFilter::RunningGames,
+        _ => GameFilter::AllGames,
+    };
+    let list_games_response = list_games(
+        filter,
+        page_size.unwrap_or_default(),
+        page_token.unwrap_or_default().to_string(),
+    )
+    .await;
+    match list_games_response {
+        Ok(list_games_reply) => Ok(json!({
+            "error": false,
+            "response": list_games_reply
         })
-        .to_string(),
+        .to_string()),
+        Err(err) => Err(error_response(&err)),
     }
 }
 
+/// Server-sent events with the game state of every tick until the game is over. Like the
+/// WatchGame stream it reads, a slow client misses the oldest states.
+/// `EventSource` can't set headers, so the session token can be passed as `?token=`.
+#[get("/watch/<game_id>")]
+async fn watch(game_id: &str, session: SessionToken) -> Result<EventStream![], ErrorResponse> {
+    let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
+    let request = session.authorize(WatchGameRequest {
+        game_id: game_id.to_string(),
+    });
+    let mut stream = client
+        .watch_game(request)
+        .await
+        .map_err(|err| error_response(&err))?
+        .into_inner();
+
+    Ok(EventStream! {
+
+        loop {
+            match stream.message().await {
+                Ok(Some(reply)) => yield Event::data(json!({
+                    "error": false,
+                    "response": reply.game_state
+                }).to_string()),
+                Ok(None) => break,
+                Err(err) => {
+                    yield Event::data(json!({
+                        "error": true,
+                        "response": err.to_string()
+                    }).to_string());
+                    break;
+                }
+            }
+        }
+    })
+}
+
 #[rocket::main]
-async fn main() -> Result<(), rocket::Error> {
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let _rocket = rocket::build()
         .mount(
             "/",
-            routes![index, delay, create, join, status, update, start,],
+            routes![
+                index,
+                delay,
+                create,
+                configure,
+                kick,
+                transfer_host,
+                join,
+                leave,
+                status,
+                update,
+                start,
+                restart,
+                pause,
+                resume,
+                watch,
+                games,
+            ],
         )
+        .register("/", catchers![unauthorized])
         .attach(CORS)
         .launch()
         .await?;
     Ok(())
 }
 
-async fn create_game(height: u32, width: u32, tick: u32) -> String {
+async fn create_game(request: CreateRequest) -> ApiResult {
+    let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
+    let request = tonic::Request::new(request);
+
+    match client.create(request).await {
+        Ok(response) => Ok(json!({
+            "error": false,
+            "response": response.into_inner()
+        })
+        .to_string()),
+        Err(err) => Err(error_response(&err)),
+    }
+}
+
+async fn configure_game(request: ConfigureRequest, session: SessionToken) -> Result<(), Status> {
+    let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
+
+    client
+        .configure(session.authorize(request))
+        .await
+        .map(|_| ())
+}
+
+async fn kick_player(
+    game_id: String,
+    session: SessionToken,
+    player_id: String,
+) -> Result<(), Status> {
+    let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
+
+    let request = session.authorize(KickRequest { game_id, player_id });
+
+    client.kick(request).await.map(|_| ())
+}
+
+async fn transfer_host_role(
+    game_id: String,
+    session: SessionToken,
+    new_host_id: String,
+) -> Result<(), Status> {
     let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
-    let request = tonic::Request::new(CreateRequest {
-        height: height,
-        width: width,
-        tick_duration_millis: tick,
-    });
 
-    let response = client.create(request).await.unwrap();
+    let request = session.authorize(TransferHostRequest {
+        game_id,
+        new_host_id,
+    });
 
-    println!("RESPONSE={:?}", response);
-    response.into_inner().game_id
+    client.transfer_host(request).await.map(|_| ())
 }
 
-async fn join_game(game_id: String) -> String {
+async fn join_game(
+    game_id: String,
+    profile: PlayerProfile,
+    mode: JoinMode,
+    team: u32,
+) -> ApiResult {
     let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
 
-    let request = tonic::Request::new(JoinRequest { game_id });
+    let request = tonic::Request::new(JoinRequest {
+        game_id,
+        profile: Some(profile),
+        mode: mode.into(),
+        team,
+    });
 
-    let response = client.join(request).await.unwrap();
+    let response = client
+        .join(request)
+        .await
+        .map_err(|err| error_response(&err))?;
 
     let json_response = json!({
         "error": 0,
         "response": response.into_inner()
     });
-    json_response.to_string()
+    Ok(json_response.to_string())
 }
 
-async fn start_game(game_id: String, user_id: String) {
+async fn leave_game(game_id: String, session: SessionToken) -> Result<(), Status> {
     let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
 
-    let request = tonic::Request::new(StartRequest { game_id, user_id });
+    let request = session.authorize(LeaveRequest { game_id });
 
-    let _response = client.start(request).await.unwrap();
+    client.leave(request).await.map(|_| ())
+}
+
+async fn start_game(game_id: String, session: SessionToken) -> Result<(), Status> {
+    let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
+
+    let request = session.authorize(StartRequest { game_id });
+
+    client.start(request).await.map(|_| ())
+}
+
+async fn restart_game(game_id: String, session: SessionToken) -> Result<(), Status> {
+    let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
+
+    let request = session.authorize(RestartRequest { game_id });
+
+    client.restart(request).await.map(|_| ())
+}
+
+async fn pause_game(game_id: String, session: SessionToken) -> Result<(), Status> {
+    let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
+
+    let request = session.authorize(PauseRequest { game_id });
+
+    client.pause(request).await.map(|_| ())
+}
+
+async fn resume_game(game_id: String, session: SessionToken) -> Result<(), Status> {
+    let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
+
+    let request = session.authorize(ResumeRequest { game_id });
+
+    client.resume(request).await.map(|_| ())
 }
 
 async fn update_game(
     game_id: String,
-    user_id: String,
+    session: SessionToken,
     direction: MoveDirection,
 ) -> Result<ProtoGameState, Status> {
     let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
 
-    let request = tonic::Request::new(UpdateRequest {
+    let request = session.authorize(UpdateRequest {
         game_id,
-        user_id,
         move_direction: direction.into(),
     });
 
     match client.update(request).await {
-        Ok(update_reply) => {
-            return Ok(update_reply.into_inner().game_state.unwrap());
-        }
+        Ok(update_reply) => Ok(update_reply.into_inner().game_state.unwrap()),
         Err(err) => {
             println!("Error: {:?}", err);
-            return Err(err);
+            Err(err)
         }
     }
 }
 
-async fn game_status(game_id: String, user_id: String) -> Result<ProtoGameState, Status> {
+async fn game_status(game_id: String, session: SessionToken) -> Result<ProtoGameState, Status> {
     let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
 
-    let request = tonic::Request::new(GameStatusRequest { game_id, user_id });
+    let request = session.authorize(GameStatusRequest { game_id });
 
     match client.game_status(request).await {
-        Ok(game_status_reply) => {
-            return Ok(game_status_reply.into_inner().game_state.unwrap());
-        }
+        Ok(game_status_reply) => Ok(game_status_reply.into_inner().game_state.unwrap()),
         Err(err) => {
             println!("Error: {:?}", err);
-            return Err(err);
+            Err(err)
         }
     }
 }
+
+async fn list_games(
+    filter: GameFilter,
+    page_size: u32,
+    page_token: String,
+) -> Result<ListGamesReply, Status> {
+    let mut client = RustyClient::connect("http://[::1]:50051").await.unwrap();
+
+    let request = tonic::Request::new(ListGamesRequest {
+        filter: filter.into(),
+        page_size,
+        page_token,
+    });
+
+    client
+        .list_games(request)
+        .await
+        .map(|list_games_reply| list_games_reply.into_inner())
+}
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use crate::Point;
+
+/// The cells of a board that food can be placed on, with a random one picked in constant
+/// time. A cell's position in `cells` is kept in `positions`, so removing a cell swaps the
+/// last cell into its place.
+#[derive(Debug, Default)]
+pub(crate) struct FreeCells {
+    cells: Vec<Point>,
+    positions: HashMap<Point, usize>,
+}
+
+impl FreeCells {
+    pub(crate) fn insert(&mut self, point: Point) {
+        if self.positions.contains_key(&point) {
+            return;
+        }
+        self.positions.insert(point, self.cells.len());
+        self.cells.push(point);
+    }
+
+    pub(crate) fn remove(&mut self, point: &Point) {
+        let Some(position) = self.positions.remove(point) else {
+            return;
+        };
+        self.cells.swap_remove(position);
+        if let Some(moved) = self.cells.get(position) {
+            self.positions.insert(*moved, position);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.cells.clear();
+        self.positions.clear();
+    }
+
+    /// The free cells, in no particular order
+    #[cfg(test)]
+    pub(crate) fn cells(&self) -> &[Point] {
+        &self.cells
+    }
+
+    /// A free cell picked uniformly at random, or none when there are no free cells
+    pub(crate) fn choose(&self, rng: &mut impl Rng) -> Option<Point> {
+        match self.cells.len() {
+            0 => None,
+            len => Some(self.cells[rng.gen_range(0..len)]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::free_cells::FreeCells;
+    use crate::Point;
+
+    #[test]
+    fn insert_remove_choose() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut free_cells = FreeCells::default();
+        assert_eq!(free_cells.choose(&mut rng), None);
+
+        for x in 0..4 {
+            free_cells.insert(Point::new(x, 0));
+        }
+        free_cells.insert(Point::new(0, 0));
+        assert_eq!(free_cells.cells.len(), 4);
+
+        // Removing a cell moves the last cell into its place
+        free_cells.remove(&Point::new(1, 0));
+        free_cells.remove(&Point::new(1, 0));
+        assert_eq!(free_cells.cells.len(), 3);
+        free_cells.remove(&Point::new(3, 0));
+        assert_eq!(free_cells.cells.len(), 2);
+        for _ in 0..20 {
+            let point = free_cells.choose(&mut rng).unwrap();
+            assert!(point == Point::new(0, 0) || point == Point::new(2, 0));
+        }
+
+        free_cells.clear();
+        assert_eq!(free_cells.choose(&mut rng), None);
+    }
+}
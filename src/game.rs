@@ -1,7 +1,59 @@
-use crate::GameState;
-use crate::{requested_direction::RequestedDirection, types::Direction, GameOverReason, Point};
-use rand::Rng;
-use std::collections::{HashSet, VecDeque};
+use crate::{
+    free_cells::FreeCells, level::Level, requested_direction::RequestedDirection, types::Direction,
+    GameOverReason, Point,
+};
+use crate::{
+    BoardMode, FoodItem, FoodKind, GameError, GameMode, GamePhase, GameSettings, GameState,
+    JoinMode, PlayerProfile, RosterEntry, SeriesScore, SnakeOwner, SnakeState, SpeedEffect,
+    BONUS_POINTS,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::time::Instant;
+
+#[cfg(feature = "bench")]
+pub mod bench;
+
+/// A joined user, either playing or spectating
+struct Member {
+    /// When the user was last heard from
+    last_seen: Instant,
+    profile: PlayerProfile,
+    mode: JoinMode,
+    /// The team the player plays for in team mode
+    team: Option<u32>,
+}
+
+impl Member {
+    fn is_player(&self) -> bool {
+        self.mode == JoinMode::Play
+    }
+}
+
+/// Length of rusty at the start of a round
+pub(crate) const STARTING_LENGTH: usize = 3;
+/// Segments taken off rusty's tail by shrink food
+const SHRINK_SEGMENTS: usize = 2;
+/// Ticks that a speed change from special food lasts
+const SPEED_EFFECT_TICKS: u32 = 20;
+const SPECIAL_FOOD_KINDS: [FoodKind; 4] = [
+    FoodKind::Bonus,
+    FoodKind::Shrink,
+    FoodKind::SpeedUp,
+    FoodKind::SlowDown,
+];
+
+/// A player's own snake in arena mode, or a team's snake in team mode
+struct Snake {
+    body: Body,
+    /// Votes from the snake's player or team, counted by the next tick
+    votes: RequestedDirection,
+    /// Points scored from food this round
+    score: u32,
+    eliminated: Option<GameOverReason>,
+}
 
 struct GameStateCache {
     last_returned_game_state_version: usize,
@@ -11,54 +63,537 @@ struct GameStateCache {
 pub(crate) struct Game {
     height: i32,
     width: i32,
-    food: Point,
+    food: Vec<FoodItem>,
+    /// Food items kept on the board
+    food_count: u32,
+    /// Chance, out of 100, that a new food item is special
+    special_food_percent: u32,
+    /// Ticks a special food item stays on the board
+    special_food_lifetime: u32,
+    /// Cells new food can be placed on, kept up to date as snakes move and food comes and
+    /// goes
+    free_cells: FreeCells,
+    speed_effect: Option<SpeedEffect>,
+    /// Points scored from food by rusty this round
+    score: u32,
+    /// Seeds `rng`, so that the same seed and the same votes on every tick replay the game
+    seed: u64,
+    /// The source of every random choice the game makes
+    rng: StdRng,
     rusty: Body,
+    game_mode: GameMode,
+    /// Teams playing in team mode, numbered from 1
+    team_count: u32,
+    /// The players' snakes in arena mode or the teams' snakes in team mode, added when the
+    /// round starts
+    snakes: BTreeMap<SnakeOwner, Snake>,
+    /// The last snake standing once an arena or team game is won
+    winner: Option<SnakeOwner>,
     game_over: Option<GameOverReason>,
+    phase: GamePhase,
+    board_mode: BoardMode,
+    /// The level the board is laid out from, or an empty board when unset
+    level: Option<Arc<Level>>,
+    countdown_ends_at: Option<Instant>,
+    first_tick_at: Option<SystemTime>,
     epoch: usize,
     requested_directions: RequestedDirection,
-    users: HashSet<String>,
+    users: HashMap<String, Member>,
+    /// The user allowed to start, configure and moderate the game
+    host: Option<String>,
+    afk_timeout: Duration,
+    series: SeriesScore,
     game_state_version: usize,
     game_state_cache: GameStateCache,
 }
 
 impl Game {
-    pub(crate) fn new(height: i32, width: i32) -> Self {
-        Self {
+    /// A game in the lobby, laid out from the level when one is given
+    pub(crate) fn new(settings: &GameSettings, level: Option<Arc<Level>>) -> Self {
+        let (height, width) = (settings.height, settings.width);
+        let (rusty, food) = Self::starting_board(height, width, level.as_deref());
+        let seed = Self::choose_seed(settings);
+        let mut game = Self {
             height,
             width,
-            food: Point::new(width / 2, height / 2),
-            rusty: Body::new(height / 2),
+            food: vec![FoodItem::regular(food)],
+            food_count: settings.food_count,
+            special_food_percent: settings.special_food_percent,
+            special_food_lifetime: settings.special_food_lifetime,
+            free_cells: FreeCells::default(),
+            speed_effect: None,
+            score: 0,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            rusty,
+            game_mode: settings.game_mode,
+            team_count: settings.team_count,
+            snakes: BTreeMap::new(),
+            winner: None,
             game_over: None,
+            phase: GamePhase::Lobby,
+            board_mode: settings.board_mode,
+            level,
+            countdown_ends_at: None,
+            first_tick_at: None,
             epoch: 0,
-            users: HashSet::new(),
+            users: HashMap::new(),
+            host: None,
+            afk_timeout: settings.afk_timeout,
+            series: SeriesScore {
+                round: 1,
+                total_score: 0,
+                best_score: 0,
+            },
             requested_directions: RequestedDirection::new(),
             game_state_version: 1,
             game_state_cache: GameStateCache {
                 last_returned_game_state_version: 0,
                 last_returned_game_state: None,
             },
-        }
+        };
+        game.rebuild_free_cells();
+        game.refill_food();
+        game
     }
 
     pub(crate) fn get_dimensions(&self) -> (u32, u32) {
         (self.width as u32, self.height as u32)
     }
 
-    pub(crate) async fn add_user(&mut self, user_id: String) -> bool {
-        self.users.insert(user_id)
+    /// Adds the user. A player becomes the host if the game doesn't have one. Display names
+    /// are unique among players and spectators, ignoring case.
+    pub(crate) async fn add_user(
+        &mut self,
+        user_id: String,
+        profile: PlayerProfile,
+        mode: JoinMode,
+    ) -> Result<(), GameError> {
+        let display_name = profile.display_name.to_lowercase();
+        let is_name_taken = self
+            .users
+            .values()
+            .any(|member| member.profile.display_name.to_lowercase() == display_name);
+        if is_name_taken {
+            return Err(GameError::NameTaken(profile.display_name));
+        }
+
+        let member = Member {
+            last_seen: Instant::now(),
+            profile,
+            mode,
+            team: (mode == JoinMode::Play).then(|| self.smallest_team()),
+        };
+        if self.users.insert(user_id.clone(), member).is_none() {
+            if mode == JoinMode::Play {
+                self.host.get_or_insert(user_id);
+            }
+            self.game_state_version += 1;
+        }
+        Ok(())
+    }
+
+    /// The team with the fewest players, picking the lowest numbered team on a tie
+    fn smallest_team(&self) -> u32 {
+        (1..=self.team_count)
+            .min_by_key(|team| {
+                self.users
+                    .values()
+                    .filter(|member| member.team == Some(*team))
+                    .count()
+            })
+            .unwrap_or(1)
+    }
+
+    /// Checks that a player joining in the given mode can play for the team
+    pub(crate) fn check_team(&self, team: u32, mode: JoinMode) -> Result<(), GameError> {
+        if self.game_mode != GameMode::Teams {
+            return Err(GameError::InvalidArgument(
+                "Teams are only chosen in team mode".to_string(),
+            ));
+        }
+        if mode == JoinMode::Spectate {
+            return Err(GameError::InvalidArgument(
+                "Spectators don't play for a team".to_string(),
+            ));
+        }
+        if !(1..=self.team_count).contains(&team) {
+            return Err(GameError::InvalidArgument(format!(
+                "team must be between 1 and {}, got {}",
+                self.team_count, team
+            )));
+        }
+        Ok(())
+    }
+
+    /// Moves a player checked by `check_team` to the team, taking their vote with them
+    pub(crate) async fn join_team(&mut self, user_id: &str, team: u32) {
+        let Some(member) = self.users.get_mut(user_id) else {
+            return;
+        };
+        let previous_team = member.team.replace(team);
+        if let Some(previous_team) = previous_team {
+            if let Some(snake) = self.snakes.get_mut(&SnakeOwner::Team(previous_team)) {
+                snake.votes.remove_direction(user_id).await;
+            }
+        }
+        self.game_state_version += 1;
+    }
+
+    /// Moves players on teams that no longer exist to the smallest teams
+    fn rebalance_teams(&mut self) {
+        let mut user_ids: Vec<String> = self
+            .users
+            .iter()
+            .filter(|(_, member)| member.team.is_some_and(|team| team > self.team_count))
+            .map(|(user_id, _)| user_id.clone())
+            .collect();
+        user_ids.sort();
+        for user_id in user_ids {
+            let team = self.smallest_team();
+            self.users.get_mut(&user_id).unwrap().team = Some(team);
+        }
+    }
+
+    pub(crate) fn is_host(&self, user_id: &str) -> bool {
+        self.host.as_deref() == Some(user_id)
+    }
+
+    /// Hands the host role to another player
+    pub(crate) fn transfer_host(&mut self, new_host_id: &str) -> Result<(), GameError> {
+        match self.users.get(new_host_id) {
+            None => {
+                return Err(GameError::InvalidArgument(format!(
+                    "User {} has not joined the game",
+                    new_host_id
+                )))
+            }
+            Some(member) if !member.is_player() => {
+                return Err(GameError::InvalidArgument(format!(
+                    "User {} is a spectator",
+                    new_host_id
+                )))
+            }
+            Some(_) => {}
+        }
+        self.host = Some(new_host_id.to_string());
+        Ok(())
+    }
+
+    /// Counts the players, leaving out spectators
+    pub(crate) fn num_users(&self) -> u32 {
+        self.users
+            .values()
+            .filter(|member| member.is_player())
+            .count() as u32
+    }
+
+    fn num_spectators(&self) -> u32 {
+        self.users
+            .values()
+            .filter(|member| !member.is_player())
+            .count() as u32
     }
 
-    pub(crate) async fn user_has_joined_game(&self, user_id: String) -> bool {
-        self.users.contains(&user_id)
+    pub(crate) fn is_spectator(&self, user_id: &str) -> bool {
+        self.users
+            .get(user_id)
+            .is_some_and(|member| !member.is_player())
     }
 
+    /// Records that the user is still playing. Returns false if the user had not joined.
+    pub(crate) fn heartbeat(&mut self, user_id: &str) -> bool {
+        match self.users.get_mut(user_id) {
+            Some(member) => {
+                member.last_seen = Instant::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the user has gone longer than the AFK timeout without a heartbeat
+    fn is_idle(&self, member: &Member, now: Instant) -> bool {
+        now.saturating_duration_since(member.last_seen) >= self.afk_timeout
+    }
+
+    /// Counts the idle players, leaving out spectators
+    fn num_idle_users(&self) -> u32 {
+        let now = Instant::now();
+        self.users
+            .values()
+            .filter(|member| member.is_player() && self.is_idle(member, now))
+            .count() as u32
+    }
+
+    fn roster(&self) -> Vec<RosterEntry> {
+        let now = Instant::now();
+        let mut roster: Vec<RosterEntry> = self
+            .users
+            .iter()
+            .map(|(user_id, member)| RosterEntry {
+                user_id: user_id.clone(),
+                profile: member.profile.clone(),
+                is_host: self.is_host(user_id),
+                is_idle: self.is_idle(member, now),
+                is_spectator: !member.is_player(),
+                team: member.team.filter(|_| self.game_mode == GameMode::Teams),
+            })
+            .collect();
+        roster.sort_by(|a, b| a.profile.display_name.cmp(&b.profile.display_name));
+        roster
+    }
+
+    pub(crate) fn phase(&self) -> GamePhase {
+        self.phase
+    }
+
+    /// Ticks played this round
+    pub(crate) fn epoch(&self) -> usize {
+        self.epoch
+    }
+
+    /// Removes the user and their vote. Returns false if the user had not joined.
+    /// When the host leaves, the most recently active player becomes the host.
+    pub(crate) async fn remove_user(&mut self, user_id: &str) -> bool {
+        self.requested_directions.remove_direction(user_id).await;
+        let removed = self.users.remove(user_id);
+        let is_removed = removed.is_some();
+        if let Some(member) = removed {
+            let owner = match self.game_mode {
+                GameMode::Teams => member.team.map(SnakeOwner::Team),
+                _ => Some(SnakeOwner::Player(user_id.to_string())),
+            };
+            let is_team_empty = !self
+                .users
+                .values()
+                .any(|other| other.is_player() && other.team == member.team);
+            if let Some(snake) = owner.and_then(|owner| self.snakes.get_mut(&owner)) {
+                snake.votes.remove_direction(user_id).await;
+                // The snake stays listed, and the next tick decides if the game is won
+                let is_abandoned = self.game_mode == GameMode::Arena || is_team_empty;
+                if is_abandoned && snake.eliminated.is_none() {
+                    snake.eliminated = Some(GameOverReason::Abandoned);
+                    let left_board = snake.body.body.iter().copied().collect();
+                    self.refresh_free_cells(left_board);
+                }
+            }
+            if self.is_host(user_id) {
+                self.host = self
+                    .users
+                    .iter()
+                    .filter(|(_, member)| member.is_player())
+                    .max_by_key(|(_, member)| member.last_seen)
+                    .map(|(user_id, _)| user_id.clone());
+            }
+            self.game_state_version += 1;
+        }
+        is_removed
+    }
+
+    pub(crate) fn user_has_joined(&self, user_id: &str) -> bool {
+        self.users.contains_key(user_id)
+    }
+
+    /// Records the user's vote
     pub(crate) async fn add_user_direction(&mut self, user_id: String, direction: Direction) {
-        self.requested_directions
-            .add_direction(&user_id, direction)
-            .await
+        match self.game_mode {
+            GameMode::CrowdVote => {
+                self.requested_directions
+                    .add_direction(&user_id, direction)
+                    .await
+            }
+            // Players without a snake, who joined after the round started, steer nothing
+            GameMode::Arena | GameMode::Teams => {
+                if let Some(snake) = self.snake_of(&user_id) {
+                    snake.votes.add_direction(&user_id, direction).await;
+                }
+            }
+        }
+    }
+
+    /// Moves the game to the next phase of its lifecycle. Transitions that would restart
+    /// a started game, skip the countdown or revive a finished game are rejected.
+    pub(crate) fn transition_to(&mut self, next: GamePhase) -> Result<(), GameError> {
+        use GamePhase::*;
+
+        match (self.phase, next) {
+            (Lobby | Paused, Countdown)
+            | (Countdown, Running)
+            | (Countdown | Running, Paused)
+            | (Lobby | Countdown | Running | Paused, Finished)
+            | (Finished, Lobby) => {
+                self.phase = next;
+                if next != Countdown {
+                    self.countdown_ends_at = None;
+                    self.first_tick_at = None;
+                }
+                if next == Finished {
+                    let score = self.score;
+                    self.series.total_score += score;
+                    self.series.best_score = self.series.best_score.max(score);
+                }
+                self.game_state_version += 1;
+                Ok(())
+            }
+            (Finished, _) => Err(GameError::GameOver),
+            (Countdown | Running, Countdown | Running) => Err(GameError::AlreadyStarted),
+            (Lobby, Running | Paused) => Err(GameError::NotStarted),
+            (from, to) => Err(GameError::InvalidTransition { from, to }),
+        }
+    }
+
+    /// Ends the game because every player left
+    pub(crate) fn abandon(&mut self) {
+        if self.transition_to(GamePhase::Finished).is_ok() {
+            self.game_over = Some(GameOverReason::Abandoned);
+        }
+    }
+
+    /// Sets up a new round of a finished game with the same players. The series score
+    /// carries over, and the round's seed is drawn from the last round's random choices.
+    pub(crate) async fn restart(&mut self) -> Result<(), GameError> {
+        self.transition_to(GamePhase::Lobby)?;
+        let seed = self.rng.gen();
+        self.reseed(seed);
+        self.reset_board().await;
+        self.series.round += 1;
+        Ok(())
+    }
+
+    /// Changes the settings of a game that is still in the lobby
+    pub(crate) async fn configure(
+        &mut self,
+        settings: &GameSettings,
+        level: Option<Arc<Level>>,
+    ) -> Result<(), GameError> {
+        match self.phase {
+            GamePhase::Lobby => {}
+            GamePhase::Finished => return Err(GameError::GameOver),
+            _ => return Err(GameError::AlreadyStarted),
+        }
+        self.height = settings.height;
+        self.width = settings.width;
+        self.afk_timeout = settings.afk_timeout;
+        self.board_mode = settings.board_mode;
+        self.game_mode = settings.game_mode;
+        self.team_count = settings.team_count;
+        self.rebalance_teams();
+        self.reseed(Self::choose_seed(settings));
+        self.food_count = settings.food_count;
+        self.special_food_percent = settings.special_food_percent;
+        self.special_food_lifetime = settings.special_food_lifetime;
+        self.level = level;
+        self.reset_board().await;
+        Ok(())
+    }
+
+    /// The seed from the settings, or a random one when none was chosen
+    fn choose_seed(settings: &GameSettings) -> u64 {
+        settings.seed.unwrap_or_else(|| rand::thread_rng().gen())
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Where rusty and the food start on the level, or on an empty board
+    fn starting_board(height: i32, width: i32, level: Option<&Level>) -> (Body, Point) {
+        match level {
+            Some(level) => (
+                Body::starting_at(level.start, level.start_direction),
+                level.food,
+            ),
+            None => (Body::new(height / 2), Point::new(width / 2, height / 2)),
+        }
+    }
+
+    async fn reset_board(&mut self) {
+        let (rusty, food) = Self::starting_board(self.height, self.width, self.level.as_deref());
+        self.rusty = rusty;
+        self.food = vec![FoodItem::regular(food)];
+        self.snakes.clear();
+        self.rebuild_free_cells();
+        self.refill_food();
+        self.speed_effect = None;
+        self.score = 0;
+        self.winner = None;
+        self.game_over = None;
+        self.epoch = 0;
+        self.requested_directions.clear().await;
+        self.game_state_version += 1;
+    }
+
+    fn is_wall(&self, point: &Point) -> bool {
+        self.level
+            .as_ref()
+            .is_some_and(|level| level.walls.contains(point))
+    }
+
+    /// Cells rusty can fill, which is the whole board apart from the walls
+    pub(crate) fn max_spaces(&self) -> usize {
+        let walls = self.level.as_ref().map_or(0, |level| level.walls.len());
+        (self.width * self.height) as usize - walls
+    }
+
+    /// The size of the board when rusty wraps around its edges
+    fn wrap_size(&self) -> Option<(i32, i32)> {
+        match self.board_mode {
+            BoardMode::Bounded => None,
+            BoardMode::Wrap => Some((self.width, self.height)),
+        }
+    }
+
+    /// The time between ticks, changed by a speed effect while it lasts
+    pub(crate) fn tick_duration_millis(&self, base_millis: u64) -> u64 {
+        match self.speed_effect.map(|effect| effect.kind) {
+            Some(FoodKind::SpeedUp) => (base_millis / 2).max(1),
+            Some(FoodKind::SlowDown) => base_millis * 2,
+            _ => base_millis,
+        }
+    }
+
+    /// Starts counting down to the first tick. An arena round gives every player a snake
+    /// when it starts.
+    pub(crate) fn start_countdown(&mut self, countdown: Duration) -> Result<(), GameError> {
+        let snakes = match (self.phase, self.game_mode) {
+            (GamePhase::Lobby, GameMode::Arena | GameMode::Teams) => Some(self.spawn_snakes()?),
+            _ => None,
+        };
+        self.transition_to(GamePhase::Countdown)?;
+        if let Some(snakes) = snakes {
+            self.snakes = snakes;
+            // Food laid out for the empty board may be under a snake now
+            self.food.retain(|food| {
+                !self
+                    .snakes
+                    .values()
+                    .any(|snake| snake.body.occupies(&food.position))
+            });
+            self.rebuild_free_cells();
+            self.refill_food();
+        }
+        self.countdown_ends_at = Some(Instant::now() + countdown);
+        self.first_tick_at = Some(SystemTime::now() + countdown);
+        Ok(())
+    }
+
+    fn countdown_remaining(&self) -> Option<Duration> {
+        self.countdown_ends_at
+            .map(|ends_at| ends_at.saturating_duration_since(Instant::now()))
     }
 
     pub(crate) async fn tick(&mut self, max_spaces: usize) -> Option<GameOverReason> {
+        // The first tick after the countdown starts the game. Ticks are ignored
+        // while the game is not running.
+        if self.phase == GamePhase::Countdown {
+            let _ = self.transition_to(GamePhase::Running);
+        }
+        if self.phase != GamePhase::Running {
+            return self.game_over.clone();
+        }
+
         self.epoch += 1;
         self.game_state_version += 1;
         // Check if game previously failed
@@ -66,6 +601,23 @@ impl Game {
             return self.game_over.clone();
         }
 
+        self.wear_off_effects();
+        match self.game_mode {
+            GameMode::CrowdVote => self.move_rusty(max_spaces).await,
+            GameMode::Arena | GameMode::Teams => self.move_snakes().await,
+        }
+
+        self.refill_food();
+        self.end_if_board_is_full();
+
+        if self.game_over.is_some() {
+            let _ = self.transition_to(GamePhase::Finished);
+        }
+        self.game_over.clone()
+    }
+
+    /// Moves rusty in the direction with the most votes
+    async fn move_rusty(&mut self, max_spaces: usize) {
         // Get user selected direction if available, else continue in same direction
         let direction = match self.requested_directions.calculate_direction().await {
             Some(user_selected_direction) => user_selected_direction,
@@ -75,61 +627,358 @@ impl Game {
         self.requested_directions.clear().await;
 
         // move rusty, rusty will grow if it overlaps with food
-        let did_grow = self.rusty.move_in_direction(direction, self.food);
+        let food_positions: Vec<Point> = self.food.iter().map(|food| food.position).collect();
+        let mut moved_over = Self::tail_segments(&self.rusty);
+        let did_grow = self
+            .rusty
+            .move_in_direction(direction, &food_positions, self.wrap_size());
+        if did_grow {
+            if let Some(kind) = self.take_food_at(self.rusty.head()) {
+                if let Some(effect) = Self::eat(kind, &mut self.rusty, &mut self.score) {
+                    self.speed_effect = Some(effect);
+                }
+            }
+        }
+        moved_over.push(self.rusty.head());
+        self.refresh_free_cells(moved_over);
 
         // Check if the player has won
         if self.rusty.body.len() == max_spaces {
             self.game_over = Some(GameOverReason::Winner);
         }
 
-        // Check out of bounds
-        let head_position = self.rusty.head();
-        if head_position.x < 0
-            || head_position.y < 0
-            || head_position.x >= self.width
-            || head_position.y >= self.height
-        {
-            self.game_over = Some(GameOverReason::OutOfBounds);
+        if let Some(reason) = self.crash(&self.rusty) {
+            self.game_over = Some(reason);
         }
+    }
 
-        // Check if head overlaps the body
-        if self.rusty.is_collide_with_self() {
-            self.game_over = Some(GameOverReason::CollideWithSelf);
+    /// Moves every snake still in play in the direction its player or team voted for, then
+    /// takes out the snakes that crashed. Snakes meeting head to head are both out, and a
+    /// snake running into another snake's body is out. The last snake standing wins.
+    async fn move_snakes(&mut self) {
+        let food_positions: Vec<Point> = self.food.iter().map(|food| food.position).collect();
+        let wrap_size = self.wrap_size();
+        let in_play: Vec<SnakeOwner> = self
+            .snakes
+            .iter()
+            .filter(|(_, snake)| snake.eliminated.is_none())
+            .map(|(owner, _)| owner.clone())
+            .collect();
+        let mut moved_over = Vec::new();
+        for owner in &in_play {
+            let snake = self.snakes.get_mut(owner).unwrap();
+            let direction = match snake.votes.calculate_direction().await {
+                Some(voted_direction) => voted_direction,
+                None => snake.body.direction,
+            };
+            snake.votes.clear().await;
+            moved_over.extend(Self::tail_segments(&snake.body));
+            snake
+                .body
+                .move_in_direction(direction, &food_positions, wrap_size);
+            // Snakes meeting head to head on food are both out, so the first one eats it
+            let head = snake.body.head();
+            moved_over.push(head);
+            if let Some(kind) = self.take_food_at(head) {
+                let snake = self.snakes.get_mut(owner).unwrap();
+                if let Some(effect) = Self::eat(kind, &mut snake.body, &mut snake.score) {
+                    self.speed_effect = Some(effect);
+                }
+            }
         }
 
-        if did_grow {
-            self.generate_new_food();
+        // Every snake moves before any is taken out, so crashes are decided on the new
+        // positions
+        let eliminated: Vec<(SnakeOwner, GameOverReason)> = in_play
+            .iter()
+            .filter_map(|owner| {
+                let body = &self.snakes[owner].body;
+                let reason = self.crash(body).or_else(|| {
+                    in_play
+                        .iter()
+                        .filter(|other| *other != owner)
+                        .find_map(|other| Self::hit_snake(body.head(), &self.snakes[other].body))
+                });
+                reason.map(|reason| (owner.clone(), reason))
+            })
+            .collect();
+        for (owner, reason) in eliminated {
+            let snake = self.snakes.get_mut(&owner).unwrap();
+            snake.eliminated = Some(reason);
+            // Snakes that are out no longer cover the board
+            moved_over.extend(snake.body.body.iter().copied());
+        }
+        self.refresh_free_cells(moved_over);
+
+        let mut remaining = self
+            .snakes
+            .iter()
+            .filter(|(_, snake)| snake.eliminated.is_none());
+        match (self.snakes.len(), remaining.next(), remaining.next()) {
+            // A lone snake plays on until it is out
+            (1, None, _) => {
+                self.game_over = self.snakes.values().next().unwrap().eliminated.clone();
+            }
+            (_, Some((owner, _)), None) if self.snakes.len() > 1 => {
+                self.winner = Some(owner.clone());
+                self.game_over = Some(GameOverReason::Winner);
+            }
+            (_, None, _) => self.game_over = Some(GameOverReason::Draw),
+            _ => {}
         }
-        self.game_over.clone()
     }
 
-    fn generate_new_food(&mut self) {
-        // Pick a new food position at random that doesn't overlap rusty
-        let mut new_food_point = Self::random_point(self.width, self.height);
-        let mut retries = 0;
+    /// Why the head of a snake ran into the other snake, if it did
+    fn hit_snake(head: Point, other: &Body) -> Option<GameOverReason> {
+        if other.head() == head {
+            Some(GameOverReason::HeadToHead)
+        } else if other.occupies(&head) {
+            Some(GameOverReason::HitSnake)
+        } else {
+            None
+        }
+    }
 
-        while self.rusty.body.contains(&new_food_point) {
-            new_food_point = Self::random_point(self.width, self.height);
-            retries += 1;
+    /// Why a snake that just moved is out, leaving out other snakes
+    fn crash(&self, body: &Body) -> Option<GameOverReason> {
+        let head = body.head();
+        if body.is_collide_with_self() {
+            Some(GameOverReason::CollideWithSelf)
+        } else if self.is_wall(&head) {
+            Some(GameOverReason::HitWall)
+        } else if head.x < 0 || head.y < 0 || head.x >= self.width || head.y >= self.height {
+            Some(GameOverReason::OutOfBounds)
+        } else {
+            None
+        }
+    }
 
-            // Randomly selecting a new food position should be good enough but a different
-            // solution should be used if it takes too many attempts
-            if retries > self.height * self.width * 2 {
-                panic!("Randomly selecting a new food position is taking too long!");
+    /// Gives every player in arena mode, or every team with players in team mode, a snake on
+    /// evenly spread rows, alternately heading east from the west edge and west from the
+    /// east edge
+    fn spawn_snakes(&self) -> Result<BTreeMap<SnakeOwner, Snake>, GameError> {
+        let mut players: Vec<(&String, &Member)> = self
+            .users
+            .iter()
+            .filter(|(_, member)| member.is_player())
+            .collect();
+        // By display name rather than the random user ids, so that a seeded game replays
+        players.sort_by_key(|(_, member)| member.profile.display_name.to_lowercase());
+        let owners: Vec<SnakeOwner> = match self.game_mode {
+            GameMode::Teams => {
+                let mut teams: Vec<SnakeOwner> = players
+                    .iter()
+                    .filter_map(|(_, member)| member.team.map(SnakeOwner::Team))
+                    .collect();
+                teams.sort();
+                teams.dedup();
+                teams
             }
+            _ => players
+                .iter()
+                .map(|(user_id, _)| SnakeOwner::Player(user_id.to_string()))
+                .collect(),
+        };
+        let count = owners.len() as i32;
+        if count > self.height {
+            return Err(GameError::InvalidArgument(format!(
+                "The board fits at most {} snakes, {} are needed",
+                self.height, count
+            )));
         }
 
-        self.food = new_food_point;
+        let tail_x = STARTING_LENGTH as i32 - 1;
+        let snakes = owners.into_iter().enumerate().map(|(index, owner)| {
+            let index = index as i32;
+            let y = (2 * index + 1) * self.height / (2 * count);
+            let body = match index % 2 {
+                0 => Body::starting_at(Point::new(tail_x, y), Direction::East),
+                _ => Body::starting_at(Point::new(self.width - 1 - tail_x, y), Direction::West),
+            };
+            let snake = Snake {
+                body,
+                votes: RequestedDirection::new(),
+                score: 0,
+                eliminated: None,
+            };
+            (owner, snake)
+        });
+        Ok(snakes.collect())
     }
 
-    fn random_point(max_x: i32, max_y: i32) -> Point {
-        Point::new(
-            rand::thread_rng().gen_range(0..max_x),
-            rand::thread_rng().gen_range(0..max_y),
-        )
+    /// The snake the user votes on, which is their own in arena mode or their team's in
+    /// team mode
+    fn snake_of(&mut self, user_id: &str) -> Option<&mut Snake> {
+        let owner = match self.game_mode {
+            GameMode::CrowdVote => return None,
+            GameMode::Arena => SnakeOwner::Player(user_id.to_string()),
+            GameMode::Teams => SnakeOwner::Team(self.users.get(user_id)?.team?),
+        };
+        self.snakes.get_mut(&owner)
+    }
+
+    /// Removes the food item at the point, returning what kind it was
+    fn take_food_at(&mut self, point: Point) -> Option<FoodKind> {
+        let index = self.food.iter().position(|food| food.position == point)?;
+        Some(self.food.remove(index).kind)
+    }
+
+    /// Applies the food to the snake that ate it, returning the speed change it causes
+    fn eat(kind: FoodKind, body: &mut Body, score: &mut u32) -> Option<SpeedEffect> {
+        match kind {
+            FoodKind::Regular => *score += 1,
+            FoodKind::Bonus => *score += BONUS_POINTS,
+            // The segment grown by eating is taken off too
+            FoodKind::Shrink => body.shrink(SHRINK_SEGMENTS + 1),
+            FoodKind::SpeedUp | FoodKind::SlowDown => {
+                *score += 1;
+                return Some(SpeedEffect {
+                    kind,
+                    ticks_left: SPEED_EFFECT_TICKS,
+                });
+            }
+        }
+        None
+    }
+
+    /// The snakes on the board, which is rusty, or the arena snakes still in play
+    fn snake_bodies(&self) -> impl Iterator<Item = &Body> {
+        let rusty = (self.game_mode == GameMode::CrowdVote).then_some(&self.rusty);
+        let snakes = self
+            .snakes
+            .values()
+            .filter(|snake| snake.eliminated.is_none())
+            .map(|snake| &snake.body);
+        rusty.into_iter().chain(snakes)
+    }
+
+    /// Counts down the speed effect and special food, removing what has run out
+    fn wear_off_effects(&mut self) {
+        self.speed_effect = self
+            .speed_effect
+            .filter(|effect| effect.ticks_left > 1)
+            .map(|effect| SpeedEffect {
+                ticks_left: effect.ticks_left - 1,
+                ..effect
+            });
+        for food in &mut self.food {
+            food.ticks_left = food.ticks_left.map(|ticks_left| ticks_left - 1);
+        }
+        let expired = self
+            .food
+            .iter()
+            .filter(|food| food.ticks_left == Some(0))
+            .map(|food| food.position)
+            .collect();
+        self.food.retain(|food| food.ticks_left != Some(0));
+        self.refresh_free_cells(expired);
+    }
+
+    /// Places new food until the board has the configured number of items or no free cells
+    fn refill_food(&mut self) {
+        while self.food.len() < self.food_count as usize {
+            let Some(position) = self.generate_new_food() else {
+                break;
+            };
+            let kind = match self.rng.gen_range(0..100) < self.special_food_percent {
+                true => SPECIAL_FOOD_KINDS[self.rng.gen_range(0..SPECIAL_FOOD_KINDS.len())],
+                false => FoodKind::Regular,
+            };
+            self.food.push(FoodItem {
+                position,
+                kind,
+                ticks_left: (kind != FoodKind::Regular).then_some(self.special_food_lifetime),
+            });
+            self.free_cells.remove(&position);
+        }
+    }
+
+    /// A free cell picked uniformly at random, or none when the board is full
+    fn generate_new_food(&mut self) -> Option<Point> {
+        self.free_cells.choose(&mut self.rng)
     }
 
-    pub(crate) async fn into_game_state(&self) -> GameState {
+    /// Whether food can be placed on the point, which must be on the board and not overlap
+    /// a snake, a wall or other food
+    fn is_free(&self, point: &Point) -> bool {
+        point.x >= 0
+            && point.y >= 0
+            && point.x < self.width
+            && point.y < self.height
+            && !self.snake_bodies().any(|body| body.occupies(point))
+            && !self.is_wall(point)
+            && !self.food.iter().any(|food| food.position == *point)
+    }
+
+    /// Updates `free_cells` for points that a snake or food may have come onto or left
+    fn refresh_free_cells(&mut self, points: Vec<Point>) {
+        for point in points {
+            match self.is_free(&point) {
+                true => self.free_cells.insert(point),
+                false => self.free_cells.remove(&point),
+            }
+        }
+    }
+
+    /// Fills `free_cells` from the whole board, row by row, after the board is laid out
+    fn rebuild_free_cells(&mut self) {
+        self.free_cells.clear();
+        let points = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| Point::new(x, y)))
+            .collect();
+        self.refresh_free_cells(points);
+    }
+
+    /// The last segments of a body, which are the only ones a move can leave. Eating shrink
+    /// food takes a few more off along with the segment the move leaves.
+    fn tail_segments(body: &Body) -> Vec<Point> {
+        body.body
+            .iter()
+            .rev()
+            .take(SHRINK_SEGMENTS + 1)
+            .copied()
+            .collect()
+    }
+
+    /// Ends the game once the board has no food left and no free cell to place more on, as
+    /// no snake can grow any further. Rusty wins, or in an arena or team game the longest
+    /// snake still in play, with a draw when several are the longest.
+    fn end_if_board_is_full(&mut self) {
+        if self.game_over.is_some() || !self.food.is_empty() {
+            return;
+        }
+        self.game_over = Some(GameOverReason::Winner);
+        if self.game_mode == GameMode::CrowdVote {
+            return;
+        }
+        let in_play = self
+            .snakes
+            .iter()
+            .filter(|(_, snake)| snake.eliminated.is_none());
+        let longest = in_play
+            .clone()
+            .map(|(_, snake)| snake.body.body.len())
+            .max()
+            .unwrap_or(0);
+        let mut longest_snakes = in_play.filter(|(_, snake)| snake.body.body.len() == longest);
+        match (longest_snakes.next(), longest_snakes.next()) {
+            (Some((owner, _)), None) => self.winner = Some(owner.clone()),
+            _ => self.game_over = Some(GameOverReason::Draw),
+        }
+    }
+
+    /// The level's walls, row by row
+    fn walls(&self) -> Vec<Point> {
+        let mut walls: Vec<Point> = self
+            .level
+            .as_ref()
+            .map(|level| level.walls.iter().copied().collect())
+            .unwrap_or_default();
+        walls.sort_by_key(|wall| (wall.y, wall.x));
+        walls
+    }
+
+    pub(crate) async fn to_game_state(&self) -> GameState {
         // If there have been no updates to the Game, return the previous GameState
         let cache = &self.game_state_cache;
         if self.game_state_version <= cache.last_returned_game_state_version {
@@ -137,6 +986,7 @@ impl Game {
         }
 
         let game_over = self.game_over.clone();
+        let num_idle = self.num_idle_users();
 
         let direction = match self.requested_directions.calculate_direction().await {
             Some(top_direction) => top_direction,
@@ -146,12 +996,41 @@ impl Game {
         GameState {
             tick: self.epoch,
             game_over_reason: game_over,
-            direction: direction,
-            body: self.rusty.body(),
-            num_users: self.requested_directions.len().await.try_into().unwrap(),
+            phase: self.phase,
+            board_mode: self.board_mode,
+            walls: self.walls(),
+            countdown_remaining: self.countdown_remaining(),
+            first_tick_at: self.first_tick_at,
+            direction,
+            body: match self.game_mode {
+                GameMode::CrowdVote => self.rusty.body(),
+                GameMode::Arena | GameMode::Teams => Vec::new(),
+            },
+            num_users: self.num_users(),
+            num_active: self.num_users() - num_idle,
+            num_idle,
+            num_spectators: self.num_spectators(),
+            score: self.score,
+            series: self.series.clone(),
+            roster: self.roster(),
             height: self.height,
             width: self.width,
-            food: self.food,
+            food: self.food.clone(),
+            speed_effect: self.speed_effect,
+            seed: self.seed,
+            game_mode: self.game_mode,
+            snakes: self
+                .snakes
+                .iter()
+                .map(|(owner, snake)| SnakeState {
+                    owner: owner.clone(),
+                    body: snake.body.body(),
+                    direction: snake.body.direction,
+                    score: snake.score,
+                    eliminated: snake.eliminated.clone(),
+                })
+                .collect(),
+            winner: self.winner.clone(),
         }
     }
 }
@@ -159,53 +1038,111 @@ impl Game {
 struct Body {
     direction: Direction,
     body: VecDeque<Point>,
+    /// How many segments are on each point the body covers, kept in step with `body` so
+    /// that looking up a point doesn't walk the whole body. The head shares a point with
+    /// another segment only when the body ran into itself.
+    occupied: HashMap<Point, usize>,
 }
 
 impl Body {
     pub fn new(starting_y: i32) -> Self {
-        Self {
-            direction: Direction::East,
-            body: VecDeque::from([
+        Self::from_segments(
+            Direction::East,
+            VecDeque::from([
                 Point::new(2, starting_y),
                 Point::new(1, starting_y),
                 Point::new(0, starting_y),
             ]),
+        )
+    }
+
+    /// A body made of the segments, head first
+    fn from_segments(direction: Direction, body: VecDeque<Point>) -> Self {
+        let mut occupied = HashMap::with_capacity(body.len());
+        for point in &body {
+            *occupied.entry(*point).or_insert(0) += 1;
         }
+        Self {
+            direction,
+            body,
+            occupied,
+        }
+    }
+
+    /// Rusty at its starting length, with the head at the given point and the body
+    /// trailing behind it
+    pub fn starting_at(head: Point, direction: Direction) -> Self {
+        let mut body = VecDeque::from([head]);
+        for _ in 1..STARTING_LENGTH {
+            let tail = body.back().unwrap().add_direction(&direction.opposite());
+            body.push_back(tail);
+        }
+        Self::from_segments(direction, body)
     }
 
     /// Moves the body in the specified direction. If the new head position doesn't
-    /// overlap with food, the tail is removed (doesn't grow).
+    /// overlap with food, the tail is removed (doesn't grow). Given the board's width and
+    /// height, the head wraps around to the opposite edge instead of leaving the board.
     ///
-    /// Returns true if the new head position overlaps with the food position.
-    pub(crate) fn move_in_direction(&mut self, direction: Direction, food: Point) -> bool {
+    /// Returns true if the new head position overlaps with one of the food positions.
+    pub(crate) fn move_in_direction(
+        &mut self,
+        direction: Direction,
+        food: &[Point],
+        wrap_size: Option<(i32, i32)>,
+    ) -> bool {
         self.direction = direction;
-        let new_point = self.head().add_direction(&self.direction);
+        let mut new_point = self.head().add_direction(&self.direction);
+        if let Some((width, height)) = wrap_size {
+            new_point = new_point.wrap(width, height);
+        }
         self.body.push_front(new_point);
-        let food_overlaps = new_point == food;
+        *self.occupied.entry(new_point).or_insert(0) += 1;
+        let food_overlaps = food.contains(&new_point);
 
         // Remove the tail (don't grow) if food doesn't overlap
         if !food_overlaps {
-            self.body.pop_back();
+            self.pop_tail();
         }
 
         food_overlaps
     }
 
-    pub(crate) fn head(&self) -> Point {
-        self.body.front().expect("Body should not be empty").clone()
+    /// Takes segments off the tail, keeping at least the starting length
+    fn shrink(&mut self, segments: usize) {
+        let length = self
+            .body
+            .len()
+            .saturating_sub(segments)
+            .max(STARTING_LENGTH);
+        while self.body.len() > length {
+            self.pop_tail();
+        }
     }
 
-    pub(crate) fn is_collide_with_self(&self) -> bool {
-        let mut iterator = self.body.iter();
-        let head_node = iterator.next().unwrap();
-
-        while let Some(point) = iterator.next() {
-            if point == head_node {
-                return true;
+    fn pop_tail(&mut self) {
+        let Some(tail) = self.body.pop_back() else {
+            return;
+        };
+        if let Some(count) = self.occupied.get_mut(&tail) {
+            *count -= 1;
+            if *count == 0 {
+                self.occupied.remove(&tail);
             }
         }
+    }
 
-        false
+    /// Whether a segment of the body is on the point
+    pub(crate) fn occupies(&self, point: &Point) -> bool {
+        self.occupied.contains_key(point)
+    }
+
+    pub(crate) fn head(&self) -> Point {
+        *self.body.front().expect("Body should not be empty")
+    }
+
+    pub(crate) fn is_collide_with_self(&self) -> bool {
+        self.occupied[&self.head()] > 1
     }
 
     pub(crate) fn body(&self) -> Vec<Point> {
@@ -215,13 +1152,30 @@ impl Body {
 
 #[cfg(test)]
 mod tests {
-    use crate::game::{Body, Game};
+    use crate::game::{Body, Game, SHRINK_SEGMENTS, SPEED_EFFECT_TICKS};
+    use crate::level::Level;
     use crate::output::print_world;
     use crate::types::Direction;
-    use crate::Point;
-    use std::collections::{LinkedList, VecDeque};
+    use crate::{
+        proto, BoardMode, FoodItem, FoodKind, GameError, GameMode, GameOverReason, GamePhase,
+        GameSettings, JoinMode, PlayerProfile, Point, SeriesScore, SnakeOwner, SpeedEffect,
+        BONUS_POINTS,
+    };
+    use std::collections::{HashMap, LinkedList, VecDeque};
+    use std::sync::Arc;
+    use std::time::Duration;
 
     const HEIGHT: i32 = 4;
+    const AFK_TIMEOUT: Duration = Duration::from_secs(30);
+
+    fn test_settings() -> GameSettings {
+        GameSettings {
+            height: HEIGHT,
+            width: HEIGHT,
+            afk_timeout: AFK_TIMEOUT,
+            ..GameSettings::default()
+        }
+    }
 
     #[test]
     fn rusty_new() {
@@ -237,9 +1191,9 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn into_game_state() {
-        let game = Game::new(HEIGHT, HEIGHT);
-        let game_state = game.into_game_state().await;
+    async fn to_game_state() {
+        let game = Game::new(&test_settings(), None);
+        let game_state = game.to_game_state().await;
 
         let expected_body = vec![
             Point::new(2, HEIGHT / 2),
@@ -261,8 +1215,8 @@ mod tests {
             expected_body.push_front(Point::new(n, HEIGHT / 2))
         }
 
-        let did_grow = rusty.move_in_direction(Direction::East, food);
-        assert_eq!(did_grow, false);
+        let did_grow = rusty.move_in_direction(Direction::East, &[food], None);
+        assert!(!did_grow);
         assert_eq!(rusty.body, expected_body);
     }
 
@@ -276,11 +1230,36 @@ mod tests {
             expected_body.push_front(Point::new(n, HEIGHT / 2))
         }
 
-        let did_grow = rusty.move_in_direction(Direction::East, food);
-        assert_eq!(did_grow, true);
+        let did_grow = rusty.move_in_direction(Direction::East, &[food], None);
+        assert!(did_grow);
         assert_eq!(rusty.body, expected_body);
     }
 
+    #[test]
+    fn occupancy_follows_body() {
+        let mut rusty = Body::new(HEIGHT / 2);
+        let food = [Point::new(3, HEIGHT / 2), Point::new(3, HEIGHT / 2 + 1)];
+        let moves = [
+            Direction::East,
+            Direction::South,
+            Direction::West,
+            Direction::West,
+        ];
+        for direction in moves {
+            rusty.move_in_direction(direction, &food, Some((HEIGHT, HEIGHT)));
+            for point in &rusty.body {
+                assert!(rusty.occupies(point));
+            }
+            assert_eq!(rusty.occupied.len(), rusty.body.len());
+        }
+        assert!(!rusty.occupies(&Point::new(0, HEIGHT / 2)));
+
+        rusty.shrink(SHRINK_SEGMENTS);
+        assert_eq!(rusty.body.len(), 3);
+        assert_eq!(rusty.occupied.len(), 3);
+        assert!(!rusty.occupies(&Point::new(3, HEIGHT / 2)));
+    }
+
     #[test]
     fn collide_with_self() {
         let mut rusty = Body::new(HEIGHT / 2);
@@ -292,15 +1271,786 @@ mod tests {
         }
 
         // Grow to a length of 5 to be large enough to hit self
-        rusty.move_in_direction(Direction::East, Point::new(3, HEIGHT / 2));
-        rusty.move_in_direction(Direction::East, Point::new(4, HEIGHT / 2));
-        assert_eq!(rusty.is_collide_with_self(), false);
+        rusty.move_in_direction(Direction::East, &[Point::new(3, HEIGHT / 2)], None);
+        rusty.move_in_direction(Direction::East, &[Point::new(4, HEIGHT / 2)], None);
+        assert!(!rusty.is_collide_with_self());
 
         // Move in a circle to hit self
-        rusty.move_in_direction(Direction::South, food);
-        rusty.move_in_direction(Direction::West, food);
-        rusty.move_in_direction(Direction::North, food);
-        assert_eq!(rusty.is_collide_with_self(), true);
+        rusty.move_in_direction(Direction::South, &[food], None);
+        rusty.move_in_direction(Direction::West, &[food], None);
+        rusty.move_in_direction(Direction::North, &[food], None);
+        assert!(rusty.is_collide_with_self());
         assert_eq!(rusty.body.len(), 5);
     }
+
+    #[tokio::test]
+    async fn wrap_around_board() {
+        let settings = GameSettings {
+            board_mode: BoardMode::Wrap,
+            ..test_settings()
+        };
+        let mut game = Game::new(&settings, None);
+        game.food = vec![FoodItem::regular(Point::new(0, 0))];
+        game.start_countdown(Duration::ZERO).unwrap();
+
+        // Rusty crosses the east edge twice without running out of bounds
+        let max_spaces = (HEIGHT * HEIGHT) as usize;
+        for _ in 0..2 * HEIGHT {
+            assert_eq!(game.tick(max_spaces).await, None);
+        }
+        let game_state = game.to_game_state().await;
+        assert_eq!(game_state.phase, GamePhase::Running);
+        assert_eq!(game_state.body[0], Point::new(2, HEIGHT / 2));
+        assert_eq!(game_state.board_mode, BoardMode::Wrap);
+    }
+
+    #[tokio::test]
+    async fn hit_wall() {
+        let map = "\
+########
+#......#
+#..>*..#
+#......#
+########";
+        let level = Level::parse("corridor", map).unwrap();
+        let settings = GameSettings {
+            height: 5,
+            width: 8,
+            ..test_settings()
+        };
+        let mut game = Game::new(&settings, Some(Arc::new(level)));
+        assert_eq!(game.max_spaces(), 18);
+        let game_state = game.to_game_state().await;
+        assert_eq!(game_state.walls.len(), 22);
+        assert_eq!(game_state.body[0], Point::new(3, 2));
+        assert_eq!(game_state.food, vec![FoodItem::regular(Point::new(4, 2))]);
+
+        game.start_countdown(Duration::ZERO).unwrap();
+        let max_spaces = game.max_spaces();
+        let mut game_over = None;
+        while game_over.is_none() {
+            game_over = game.tick(max_spaces).await;
+        }
+        assert_eq!(game_over, Some(GameOverReason::HitWall));
+        assert_eq!(game.to_game_state().await.body[0], Point::new(7, 2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn presence_counts() {
+        let mut game = Game::new(&test_settings(), None);
+        game.add_user(
+            "active".to_string(),
+            PlayerProfile::new("active"),
+            JoinMode::Play,
+        )
+        .await
+        .unwrap();
+        game.add_user(
+            "idle".to_string(),
+            PlayerProfile::new("idle"),
+            JoinMode::Play,
+        )
+        .await
+        .unwrap();
+
+        tokio::time::advance(AFK_TIMEOUT).await;
+        assert!(game.heartbeat("active"));
+        assert!(!game.heartbeat("unknown"));
+        game.add_user_direction("active".to_string(), Direction::South)
+            .await;
+        game.start_countdown(Duration::ZERO).unwrap();
+        game.tick(HEIGHT as usize * HEIGHT as usize).await;
+
+        // Counts are kept after the votes are flushed by the tick
+        let game_state = game.to_game_state().await;
+        assert_eq!(game_state.num_users, 2);
+        assert_eq!(game_state.num_active, 1);
+        assert_eq!(game_state.num_idle, 1);
+    }
+
+    #[tokio::test]
+    async fn restart_keeps_series_score() {
+        let mut game = Game::new(&test_settings(), None);
+        game.add_user(
+            "user".to_string(),
+            PlayerProfile::new("user"),
+            JoinMode::Play,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            game.restart().await,
+            Err(GameError::InvalidTransition {
+                from: GamePhase::Lobby,
+                to: GamePhase::Lobby
+            })
+        ));
+
+        // Eat the food in the center, then run out of bounds
+        game.food = vec![FoodItem::regular(Point::new(3, HEIGHT / 2))];
+        game.start_countdown(Duration::ZERO).unwrap();
+        let max_spaces = (HEIGHT * HEIGHT) as usize;
+        while game.tick(max_spaces).await.is_none() {}
+        let game_state = game.to_game_state().await;
+        assert_eq!(game_state.score, 1);
+        assert_eq!(game_state.series.total_score, 1);
+
+        game.restart().await.unwrap();
+        let game_state = game.to_game_state().await;
+        assert_eq!(game_state.phase, GamePhase::Lobby);
+        assert_eq!(game_state.tick, 0);
+        assert_eq!(game_state.game_over_reason, None);
+        assert_eq!(game_state.body, Body::new(HEIGHT / 2).body());
+        assert_eq!(game_state.score, 0);
+        assert_eq!(game_state.num_users, 1);
+        assert_eq!(
+            game_state.series,
+            SeriesScore {
+                round: 2,
+                total_score: 1,
+                best_score: 1
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn host_role() {
+        let mut game = Game::new(&test_settings(), None);
+        game.add_user(
+            "host".to_string(),
+            PlayerProfile::new("host"),
+            JoinMode::Play,
+        )
+        .await
+        .unwrap();
+        game.add_user(
+            "player".to_string(),
+            PlayerProfile::new("player"),
+            JoinMode::Play,
+        )
+        .await
+        .unwrap();
+        assert!(game.is_host("host"));
+        assert!(!game.is_host("player"));
+
+        assert!(matches!(
+            game.transfer_host("unknown"),
+            Err(GameError::InvalidArgument(_))
+        ));
+        game.transfer_host("player").unwrap();
+        assert!(game.is_host("player"));
+
+        // The host role passes on when the host leaves
+        game.remove_user("player").await;
+        assert!(game.is_host("host"));
+        game.remove_user("host").await;
+        game.add_user(
+            "next".to_string(),
+            PlayerProfile::new("next"),
+            JoinMode::Play,
+        )
+        .await
+        .unwrap();
+        assert!(game.is_host("next"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn roster_has_unique_names() {
+        let mut game = Game::new(&test_settings(), None);
+        let profile = PlayerProfile {
+            display_name: "Zed".to_string(),
+            color: Some("#ff8800".to_string()),
+            avatar: None,
+        };
+        game.add_user("zed".to_string(), profile.clone(), JoinMode::Play)
+            .await
+            .unwrap();
+        tokio::time::advance(AFK_TIMEOUT).await;
+        game.add_user("amy".to_string(), PlayerProfile::new("Amy"), JoinMode::Play)
+            .await
+            .unwrap();
+        let result = game
+            .add_user(
+                "other".to_string(),
+                PlayerProfile::new("zED"),
+                JoinMode::Play,
+            )
+            .await;
+        assert!(matches!(result, Err(GameError::NameTaken(name)) if name == "zED"));
+
+        let roster = game.to_game_state().await.roster;
+        let names: Vec<&str> = roster
+            .iter()
+            .map(|player| player.profile.display_name.as_str())
+            .collect();
+        assert_eq!(names, ["Amy", "Zed"]);
+        assert!(!roster[0].is_host && !roster[0].is_idle);
+        assert!(roster[1].is_host && roster[1].is_idle);
+        assert_eq!(roster[1].profile, profile);
+    }
+
+    #[tokio::test]
+    async fn spectators_are_counted_apart() {
+        let mut game = Game::new(&test_settings(), None);
+        game.add_user(
+            "watcher".to_string(),
+            PlayerProfile::new("watcher"),
+            JoinMode::Spectate,
+        )
+        .await
+        .unwrap();
+        assert!(!game.is_host("watcher"));
+        assert!(game.is_spectator("watcher"));
+
+        game.add_user(
+            "player".to_string(),
+            PlayerProfile::new("player"),
+            JoinMode::Play,
+        )
+        .await
+        .unwrap();
+        assert!(game.is_host("player"));
+        assert!(matches!(
+            game.transfer_host("watcher"),
+            Err(GameError::InvalidArgument(_))
+        ));
+
+        let game_state = game.to_game_state().await;
+        assert_eq!(game_state.num_users, 1);
+        assert_eq!(game_state.num_spectators, 1);
+        assert_eq!(game_state.roster.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn keeps_food_count_on_board() {
+        let settings = GameSettings {
+            food_count: 5,
+            special_food_percent: 100,
+            ..test_settings()
+        };
+        let game = Game::new(&settings, None);
+        let game_state = game.to_game_state().await;
+        assert_eq!(game_state.food.len(), 5);
+        // The food in front of rusty at the start is always regular
+        assert_eq!(game_state.food[0].kind, FoodKind::Regular);
+        for food in &game_state.food[1..] {
+            assert_ne!(food.kind, FoodKind::Regular);
+            assert_eq!(food.ticks_left, Some(settings.special_food_lifetime));
+            assert!(!game_state.body.contains(&food.position));
+        }
+
+        // Food is never placed on top of rusty, so a crowded board holds less of it
+        let settings = GameSettings {
+            food_count: 20,
+            ..test_settings()
+        };
+        let game = Game::new(&settings, None);
+        assert!(game.food.len() < 20);
+        assert_eq!(game.free_cells.cells().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn eat_typed_food() {
+        let mut game = Game::new(&test_settings(), None);
+        game.food = vec![FoodItem {
+            position: Point::new(3, HEIGHT / 2),
+            kind: FoodKind::Bonus,
+            ticks_left: Some(5),
+        }];
+        game.start_countdown(Duration::ZERO).unwrap();
+        game.tick(16).await;
+        let game_state = game.to_game_state().await;
+        assert_eq!(game_state.score, BONUS_POINTS);
+        assert_eq!(game_state.body.len(), 4);
+
+        // A long rusty loses segments, but never drops below its starting length
+        let mut game = Game::new(&test_settings(), None);
+        for x in 3..6 {
+            game.rusty
+                .move_in_direction(Direction::East, &[Point::new(x, HEIGHT / 2)], None);
+        }
+        game.rusty.shrink(SHRINK_SEGMENTS);
+        assert_eq!(game.rusty.body.len(), 4);
+        game.rusty.shrink(SHRINK_SEGMENTS);
+        assert_eq!(game.rusty.body.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn speed_effect_wears_off() {
+        let settings = GameSettings {
+            height: 3,
+            width: 40,
+            ..test_settings()
+        };
+        let mut game = Game::new(&settings, None);
+        game.food = vec![FoodItem {
+            position: Point::new(3, 1),
+            kind: FoodKind::SpeedUp,
+            ticks_left: Some(5),
+        }];
+        game.start_countdown(Duration::ZERO).unwrap();
+        assert_eq!(game.tick_duration_millis(100), 100);
+
+        game.tick(120).await;
+        assert_eq!(
+            game.speed_effect,
+            Some(SpeedEffect {
+                kind: FoodKind::SpeedUp,
+                ticks_left: SPEED_EFFECT_TICKS
+            })
+        );
+        assert_eq!(game.tick_duration_millis(100), 50);
+        for _ in 0..SPEED_EFFECT_TICKS {
+            game.food.clear();
+            game.tick(120).await;
+        }
+        assert_eq!(game.speed_effect, None);
+        assert_eq!(game.tick_duration_millis(100), 100);
+
+        game.speed_effect = Some(SpeedEffect {
+            kind: FoodKind::SlowDown,
+            ticks_left: 1,
+        });
+        assert_eq!(game.tick_duration_millis(100), 200);
+    }
+
+    #[tokio::test]
+    async fn special_food_expires() {
+        let mut game = Game::new(&test_settings(), None);
+        let expiring = FoodItem {
+            position: Point::new(0, 0),
+            kind: FoodKind::Shrink,
+            ticks_left: Some(2),
+        };
+        game.food = vec![expiring];
+        game.start_countdown(Duration::ZERO).unwrap();
+
+        game.tick(16).await;
+        assert_eq!(
+            game.food,
+            vec![FoodItem {
+                ticks_left: Some(1),
+                ..expiring
+            }]
+        );
+        game.tick(16).await;
+        assert_eq!(game.food.len(), 1);
+        assert_ne!(game.food[0], expiring);
+        assert_eq!(game.food[0].kind, FoodKind::Regular);
+    }
+
+    #[test]
+    fn food_is_placed_uniformly_on_free_cells() {
+        let settings = GameSettings {
+            seed: Some(7),
+            ..test_settings()
+        };
+        let mut game = Game::new(&settings, None);
+        game.food.clear();
+        game.rebuild_free_cells();
+        let mut placed = HashMap::new();
+        for _ in 0..3000 {
+            *placed.entry(game.generate_new_food().unwrap()).or_insert(0) += 1;
+        }
+        // Every cell but rusty's three is picked about as often as the others
+        assert_eq!(placed.len(), (HEIGHT * HEIGHT) as usize - 3);
+        assert!(placed.values().all(|count| (150..=320).contains(count)));
+
+        // Only the last free cell is left once rusty and the food cover the rest
+        game.food = (0..HEIGHT)
+            .flat_map(|y| (0..HEIGHT).map(move |x| Point::new(x, y)))
+            .filter(|point| !game.rusty.body.contains(point) && *point != Point::new(3, 3))
+            .map(FoodItem::regular)
+            .collect();
+        game.rebuild_free_cells();
+        assert_eq!(game.generate_new_food(), Some(Point::new(3, 3)));
+        game.food_count = game.food.len() as u32 + 1;
+        game.refill_food();
+        assert_eq!(game.food.last().unwrap().position, Point::new(3, 3));
+        assert_eq!(game.generate_new_food(), None);
+    }
+
+    /// Checks that the free cells kept up to date as the game goes on are the cells a
+    /// fresh look at the board finds free
+    fn assert_free_cells_in_sync(game: &Game) {
+        let mut free_cells = game.free_cells.cells().to_vec();
+        free_cells.sort_by_key(|point| (point.y, point.x));
+        let board_free_cells: Vec<Point> = (0..game.height)
+            .flat_map(|y| (0..game.width).map(move |x| Point::new(x, y)))
+            .filter(|point| game.is_free(point))
+            .collect();
+        assert_eq!(free_cells, board_free_cells);
+    }
+
+    #[tokio::test]
+    async fn full_board_ends_the_game() {
+        // A lone snake that fills a wrapping board has nowhere to find food and wins
+        let mut game = snake_game(GameMode::Arena, 3, 1, &["amy"]).await;
+        game.board_mode = BoardMode::Wrap;
+        game.start_countdown(Duration::ZERO).unwrap();
+        assert!(game.food.is_empty());
+        assert_eq!(game.tick(3).await, Some(GameOverReason::Winner));
+        assert_eq!(
+            game.to_game_state().await.winner,
+            Some(SnakeOwner::Player("amy".to_string()))
+        );
+
+        // Snakes of the same length filling the board draw
+        let mut game = snake_game(GameMode::Arena, 3, 2, &["amy", "bob"]).await;
+        game.board_mode = BoardMode::Wrap;
+        game.start_countdown(Duration::ZERO).unwrap();
+        assert_eq!(game.tick(6).await, Some(GameOverReason::Draw));
+        assert_eq!(game.to_game_state().await.winner, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn same_seed_replays_the_game() {
+        let settings = GameSettings {
+            width: 12,
+            height: 12,
+            food_count: 4,
+            special_food_percent: 50,
+            seed: Some(42),
+            ..test_settings()
+        };
+        let mut games = Vec::new();
+        for _ in 0..2 {
+            let mut game = Game::new(&settings, None);
+            game.add_user("amy".to_string(), PlayerProfile::new("amy"), JoinMode::Play)
+                .await
+                .unwrap();
+            game.start_countdown(Duration::ZERO).unwrap();
+            games.push(game);
+        }
+        assert_eq!(games[0].to_game_state().await.seed, 42);
+
+        // Rusty circles a square while the food, and the random kinds it comes in, are eaten
+        // and placed again in the same way in both games
+        let laps = [
+            (Direction::East, 4),
+            (Direction::South, 3),
+            (Direction::West, 4),
+            (Direction::North, 3),
+        ];
+        for (direction, ticks) in laps.into_iter().cycle().take(8) {
+            for game in &mut games {
+                game.add_user_direction("amy".to_string(), direction).await;
+            }
+            for _ in 0..ticks {
+                tokio::time::advance(Duration::from_millis(100)).await;
+                let mut replays = Vec::new();
+                for game in &mut games {
+                    let game_over_reason = game.tick(100).await;
+                    let game_state = game.to_game_state().await;
+                    replays.push((
+                        game_over_reason,
+                        game_state.body,
+                        game_state.food,
+                        game_state.score,
+                    ));
+                }
+                assert_eq!(replays[0], replays[1]);
+                assert_eq!(replays[0].0, None);
+                assert_free_cells_in_sync(&games[0]);
+            }
+        }
+
+        // The seed picked for an unseeded game replays it too
+        let settings = GameSettings {
+            width: 12,
+            height: 12,
+            food_count: 4,
+            ..test_settings()
+        };
+        let unseeded = Game::new(&settings, None).to_game_state().await;
+        let replay = Game::new(
+            &GameSettings {
+                seed: Some(unseeded.seed),
+                ..settings
+            },
+            None,
+        );
+        assert_eq!(replay.to_game_state().await.food, unseeded.food);
+
+        // The seed a game reports over gRPC replays it when sent back in a create request,
+        // including 0
+        let request = proto::CreateRequest {
+            width: 12,
+            height: 12,
+            food_count: 4,
+            seed: Some(0),
+            ..proto::CreateRequest::default()
+        };
+        let game = Game::new(&GameSettings::try_from(&request).unwrap(), None);
+        let reported = proto::GameState::from(game.to_game_state().await);
+        assert_eq!(reported.seed, 0);
+        let request = proto::CreateRequest {
+            seed: Some(reported.seed),
+            ..request
+        };
+        let replay = Game::new(&GameSettings::try_from(&request).unwrap(), None);
+        let replayed = proto::GameState::from(replay.to_game_state().await);
+        assert_eq!(replayed.food_items, reported.food_items);
+    }
+
+    /// An arena or team game on a board of the given size with the players joined
+    async fn snake_game(game_mode: GameMode, width: i32, height: i32, players: &[&str]) -> Game {
+        let settings = GameSettings {
+            width,
+            height,
+            game_mode,
+            ..test_settings()
+        };
+        let mut game = Game::new(&settings, None);
+        for &player in players {
+            game.add_user(
+                player.to_string(),
+                PlayerProfile::new(player),
+                JoinMode::Play,
+            )
+            .await
+            .unwrap();
+        }
+        game
+    }
+
+    #[tokio::test]
+    async fn arena_spawns_a_snake_per_player() {
+        let mut game = snake_game(GameMode::Arena, 10, 10, &["amy", "bob", "cat"]).await;
+        game.add_user(
+            "watcher".to_string(),
+            PlayerProfile::new("watcher"),
+            JoinMode::Spectate,
+        )
+        .await
+        .unwrap();
+        game.start_countdown(Duration::ZERO).unwrap();
+
+        let game_state = game.to_game_state().await;
+        assert!(game_state.body.is_empty());
+        let heads: Vec<(String, Point, Direction)> = game_state
+            .snakes
+            .iter()
+            .map(|snake| (snake.owner.to_string(), snake.body[0], snake.direction))
+            .collect();
+        assert_eq!(
+            heads,
+            [
+                ("amy".to_string(), Point::new(2, 1), Direction::East),
+                ("bob".to_string(), Point::new(7, 5), Direction::West),
+                ("cat".to_string(), Point::new(2, 8), Direction::East),
+            ]
+        );
+        for food in &game_state.food {
+            assert!(game_state
+                .snakes
+                .iter()
+                .all(|snake| !snake.body.contains(&food.position)));
+        }
+
+        // Every snake needs a row of its own
+        let mut game = snake_game(GameMode::Arena, 6, 4, &["a", "b", "c", "d", "e"]).await;
+        assert!(matches!(
+            game.start_countdown(Duration::ZERO),
+            Err(GameError::InvalidArgument(_))
+        ));
+        assert_eq!(game.phase(), GamePhase::Lobby);
+    }
+
+    #[tokio::test]
+    async fn arena_collisions() {
+        // amy starts heading east from (2, 1) and zed heading west from (4, 3)
+        let mut game = snake_game(GameMode::Arena, 7, 4, &["amy", "zed"]).await;
+        game.start_countdown(Duration::ZERO).unwrap();
+        game.add_user_direction("amy".to_string(), Direction::South)
+            .await;
+        assert_eq!(game.tick(28).await, None);
+        game.add_user_direction("amy".to_string(), Direction::South)
+            .await;
+        assert_eq!(game.tick(28).await, Some(GameOverReason::Draw));
+        let game_state = game.to_game_state().await;
+        assert_eq!(game_state.winner, None);
+        assert_eq!(game_state.phase, GamePhase::Finished);
+        for snake in &game_state.snakes {
+            assert_eq!(snake.body[0], Point::new(2, 3));
+            assert_eq!(snake.eliminated, Some(GameOverReason::HeadToHead));
+        }
+
+        // amy turns into the tail of zed, who is the last snake standing
+        let mut game = snake_game(GameMode::Arena, 7, 4, &["amy", "zed"]).await;
+        game.start_countdown(Duration::ZERO).unwrap();
+        let mut game_over = None;
+        for direction in [Direction::South, Direction::East, Direction::South] {
+            game.add_user_direction("amy".to_string(), direction).await;
+            game_over = game.tick(28).await;
+            assert_free_cells_in_sync(&game);
+        }
+        assert_eq!(game_over, Some(GameOverReason::Winner));
+        let game_state = game.to_game_state().await;
+        assert_eq!(
+            game_state.winner,
+            Some(SnakeOwner::Player("zed".to_string()))
+        );
+        assert_eq!(game_state.snakes[0].body[0], Point::new(3, 3));
+        assert_eq!(
+            game_state.snakes[0].eliminated,
+            Some(GameOverReason::HitSnake)
+        );
+        assert_eq!(game_state.snakes[1].eliminated, None);
+    }
+
+    #[tokio::test]
+    async fn arena_last_snake_standing() {
+        // A player leaving takes their snake out
+        let mut game = snake_game(GameMode::Arena, 10, 10, &["amy", "zed"]).await;
+        game.start_countdown(Duration::ZERO).unwrap();
+        game.remove_user("zed").await;
+        assert_free_cells_in_sync(&game);
+        assert_eq!(game.tick(100).await, Some(GameOverReason::Winner));
+        let game_state = game.to_game_state().await;
+        assert_eq!(
+            game_state.winner,
+            Some(SnakeOwner::Player("amy".to_string()))
+        );
+        assert_eq!(
+            game_state.snakes[1].eliminated,
+            Some(GameOverReason::Abandoned)
+        );
+
+        // A lone snake plays until it is out
+        let mut game = snake_game(GameMode::Arena, 10, 10, &["amy"]).await;
+        game.start_countdown(Duration::ZERO).unwrap();
+        let mut game_over = None;
+        while game_over.is_none() {
+            game_over = game.tick(100).await;
+        }
+        assert_eq!(game_over, Some(GameOverReason::OutOfBounds));
+        assert_eq!(game.to_game_state().await.winner, None);
+
+        // The next round starts without snakes until it is started
+        game.restart().await.unwrap();
+        assert!(game.to_game_state().await.snakes.is_empty());
+    }
+
+    /// The team of every player, ordered by display name
+    async fn teams(game: &Game) -> Vec<Option<u32>> {
+        let roster = game.to_game_state().await.roster;
+        roster.iter().map(|player| player.team).collect()
+    }
+
+    #[tokio::test]
+    async fn teams_are_balanced() {
+        let mut game = snake_game(GameMode::Teams, 10, 10, &["a", "b", "c"]).await;
+        assert_eq!(teams(&game).await, [Some(1), Some(2), Some(1)]);
+
+        assert!(game.check_team(2, JoinMode::Play).is_ok());
+        for (team, mode) in [
+            (0, JoinMode::Play),
+            (3, JoinMode::Play),
+            (1, JoinMode::Spectate),
+        ] {
+            assert!(matches!(
+                game.check_team(team, mode),
+                Err(GameError::InvalidArgument(_))
+            ));
+        }
+        game.join_team("c", 2).await;
+        assert_eq!(teams(&game).await, [Some(1), Some(2), Some(2)]);
+
+        // Players on a team that is dropped are moved to the smallest team
+        let settings = GameSettings {
+            game_mode: GameMode::Teams,
+            team_count: 3,
+            ..test_settings()
+        };
+        game.configure(&settings, None).await.unwrap();
+        game.join_team("b", 3).await;
+        game.join_team("c", 3).await;
+        game.configure(
+            &GameSettings {
+                team_count: 2,
+                ..settings
+            },
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(teams(&game).await, [Some(1), Some(2), Some(1)]);
+    }
+
+    #[tokio::test]
+    async fn team_votes_steer_team_snake() {
+        let mut game = snake_game(GameMode::Teams, 10, 10, &["a1", "a2", "a3", "b1"]).await;
+        for (user_id, team) in [("a1", 1), ("a2", 1), ("a3", 1), ("b1", 2)] {
+            game.join_team(user_id, team).await;
+        }
+        game.start_countdown(Duration::ZERO).unwrap();
+
+        // Each team's snake follows the majority of its own team's votes
+        let votes = [
+            ("a1", Direction::South),
+            ("a2", Direction::South),
+            ("a3", Direction::North),
+            ("b1", Direction::North),
+        ];
+        for (user_id, direction) in votes {
+            game.add_user_direction(user_id.to_string(), direction)
+                .await;
+        }
+        assert_eq!(game.tick(100).await, None);
+        let game_state = game.to_game_state().await;
+        let heads: Vec<(SnakeOwner, Point)> = game_state
+            .snakes
+            .iter()
+            .map(|snake| (snake.owner.clone(), snake.body[0]))
+            .collect();
+        assert_eq!(
+            heads,
+            [
+                (SnakeOwner::Team(1), Point::new(2, 3)),
+                (SnakeOwner::Team(2), Point::new(7, 6)),
+            ]
+        );
+
+        // A team is out when its last player leaves
+        game.remove_user("b1").await;
+        assert_eq!(game.tick(100).await, Some(GameOverReason::Winner));
+        let game_state = game.to_game_state().await;
+        assert_eq!(game_state.winner, Some(SnakeOwner::Team(1)));
+        assert_eq!(
+            game_state.snakes[1].eliminated,
+            Some(GameOverReason::Abandoned)
+        );
+    }
+
+    #[test]
+    fn phase_transitions() {
+        let mut game = Game::new(&test_settings(), None);
+        assert!(matches!(
+            game.transition_to(GamePhase::Running),
+            Err(GameError::NotStarted)
+        ));
+
+        game.start_countdown(Duration::from_secs(3)).unwrap();
+        assert!(matches!(
+            game.start_countdown(Duration::from_secs(3)),
+            Err(GameError::AlreadyStarted)
+        ));
+
+        game.transition_to(GamePhase::Running).unwrap();
+        game.transition_to(GamePhase::Paused).unwrap();
+        assert!(matches!(
+            game.transition_to(GamePhase::Paused),
+            Err(GameError::InvalidTransition {
+                from: GamePhase::Paused,
+                to: GamePhase::Paused
+            })
+        ));
+
+        game.transition_to(GamePhase::Finished).unwrap();
+        assert!(matches!(
+            game.start_countdown(Duration::from_secs(3)),
+            Err(GameError::GameOver)
+        ));
+    }
 }
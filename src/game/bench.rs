@@ -0,0 +1,183 @@
+//! Long snakes on large boards for the benchmarks, which can't reach the game otherwise.
+//! Only built with the `bench` feature.
+//!
+//! The snakes follow a path that covers a wrapping board of even height row by row, east
+//! along even rows and west along odd rows, so they can move for as long as they are shorter
+//! than the board without running into themselves.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::runtime::{self, Runtime};
+
+use crate::{
+    game::{Body, Game},
+    types::Direction,
+    BoardMode, GameSettings, Point,
+};
+
+/// The direction the path takes from the point
+fn path_direction(point: Point, width: i32) -> Direction {
+    match (point.y % 2 == 0, point.x) {
+        (true, x) if x < width - 1 => Direction::East,
+        (false, x) if x > 0 => Direction::West,
+        _ => Direction::South,
+    }
+}
+
+/// A body of the given length along the path, with the tail at the top left corner
+fn body_on_path(width: i32, height: i32, length: usize) -> Body {
+    let mut body = VecDeque::with_capacity(length);
+    let mut point = Point::new(0, 0);
+    for _ in 0..length {
+        body.push_front(point);
+        point = point
+            .add_direction(&path_direction(point, width))
+            .wrap(width, height);
+    }
+    let direction = path_direction(body[0], width);
+    Body::from_segments(direction, body)
+}
+
+/// The code replaced by tracking the points each body covers and by the free-cell index,
+/// copied as it was so the benchmarks measure against it
+impl Body {
+    fn is_collide_with_self_replaced(&self) -> bool {
+        let mut iterator = self.body.iter();
+        let head_node = iterator.next().unwrap();
+
+        for point in iterator {
+            if point == head_node {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+impl Game {
+    fn is_free_replaced(&self, point: &Point) -> bool {
+        !self
+            .snake_bodies()
+            .collect::<Vec<&Body>>()
+            .iter()
+            .any(|body| body.body.contains(point))
+            && !self.is_wall(point)
+            && !self.food.iter().any(|food| food.position == *point)
+    }
+
+    fn generate_new_food_replaced(&mut self) -> Option<Point> {
+        let free_cells = self.free_cells_replaced().count();
+        if free_cells == 0 {
+            return None;
+        }
+        let index = self.rng.gen_range(0..free_cells);
+        self.free_cells_replaced().nth(index)
+    }
+
+    fn free_cells_replaced(&self) -> impl Iterator<Item = Point> + '_ {
+        let snake_bodies: Vec<&Body> = self.snake_bodies().collect();
+        (0..self.height)
+            .flat_map(move |y| (0..self.width).map(move |x| Point::new(x, y)))
+            .filter(move |point| {
+                !snake_bodies.iter().any(|body| body.occupies(point))
+                    && !self.is_wall(point)
+                    && !self.food.iter().any(|food| food.position == *point)
+            })
+    }
+}
+
+/// A snake following the path
+pub struct LongSnake {
+    body: Body,
+    width: i32,
+    height: i32,
+}
+
+impl LongSnake {
+    pub fn new(width: i32, height: i32, length: usize) -> Self {
+        Self {
+            body: body_on_path(width, height, length),
+            width,
+            height,
+        }
+    }
+
+    /// Moves the snake one cell along the path without growing
+    pub fn advance(&mut self) {
+        let direction = path_direction(self.body.head(), self.width);
+        self.body
+            .move_in_direction(direction, &[], Some((self.width, self.height)));
+    }
+
+    pub fn is_collide_with_self(&self) -> bool {
+        self.body.is_collide_with_self()
+    }
+
+    /// `is_collide_with_self` as it was before bodies tracked the points they cover
+    pub fn is_collide_with_self_replaced(&self) -> bool {
+        self.body.is_collide_with_self_replaced()
+    }
+}
+
+/// A running crowd vote game on a wrapping board, with rusty steered along the path
+pub struct LongSnakeGame {
+    game: Game,
+    runtime: Runtime,
+}
+
+impl LongSnakeGame {
+    pub fn new(width: i32, height: i32, length: usize) -> Self {
+        let settings = GameSettings {
+            width,
+            height,
+            board_mode: BoardMode::Wrap,
+            seed: Some(1),
+            ..GameSettings::default()
+        };
+        let mut game = Game::new(&settings, None);
+        game.rusty = body_on_path(width, height, length);
+        game.food.clear();
+        game.rebuild_free_cells();
+        game.refill_food();
+        game.start_countdown(Duration::ZERO).unwrap();
+        Self {
+            game,
+            runtime: runtime::Builder::new_current_thread().build().unwrap(),
+        }
+    }
+
+    /// Rusty's last segment, the point a walk of the body reaches last
+    pub fn tail(&self) -> Point {
+        *self.game.rusty.body.back().unwrap()
+    }
+
+    /// Moves rusty one cell along the path, returning whether the game is still running
+    pub fn tick(&mut self) -> bool {
+        self.game.rusty.direction = path_direction(self.game.rusty.head(), self.game.width);
+        let max_spaces = self.game.max_spaces();
+        self.runtime.block_on(self.game.tick(max_spaces)).is_none()
+    }
+
+    /// Picks a free cell for a new food item, leaving the board as it was
+    pub fn place_food(&mut self) -> Option<Point> {
+        self.game.generate_new_food()
+    }
+
+    pub fn is_free(&self, point: &Point) -> bool {
+        self.game.is_free(point)
+    }
+
+    /// `is_free` as it was before bodies tracked the points they cover
+    pub fn is_free_replaced(&self, point: &Point) -> bool {
+        self.game.is_free_replaced(point)
+    }
+
+    /// `place_food` as it was before food was picked from the free-cell index, scanning
+    /// every cell of the board
+    pub fn place_food_replaced(&mut self) -> Option<Point> {
+        self.game.generate_new_food_replaced()
+    }
+}
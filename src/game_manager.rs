@@ -1,169 +1,1004 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock, Weak},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use tokio::sync::{oneshot, Mutex};
+use log::info;
+use tokio::{
+    sync::{broadcast, oneshot, Mutex},
+    task::JoinHandle,
+    time,
+};
 use uuid::Uuid;
 
 use crate::{
-    game_task::{GameCommand, GameTask},
+    game_task::{GameActivity, GameCommand, GameTask},
+    level::{load_levels, Level},
+    session::{Principal, SessionInterceptor, SessionStore},
     types::Direction,
-    GameError, GameState, JoinGameReply,
+    CreateGameReply, GameError, GameListFilter, GameListPage, GameSettings, GameState, GameSummary,
+    JoinGameReply, JoinMode, PlayerProfile, Responder, ServerConfig,
 };
+
+type Games = Mutex<HashMap<String, Arc<Mutex<GameTask>>>>;
+
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+
+/// A joined user's connection to a game, used to stream game states and vote on directions
+/// without looking up the game for every request.
+pub struct PlaySession {
+    pub game_states: broadcast::Receiver<GameState>,
+    pub voter: DirectionVoter,
+}
+
+/// Submits direction votes on behalf of a single user
+pub struct DirectionVoter {
+    principal: Principal,
+    game: Arc<Mutex<GameTask>>,
+}
+
+impl DirectionVoter {
+    pub fn principal(&self) -> &Principal {
+        &self.principal
+    }
+
+    pub async fn vote(&self, direction: Direction) -> Result<GameState, GameError> {
+        send_update(&self.game, self.principal.clone(), direction).await
+    }
+}
+
 pub(crate) struct GameManager {
-    games: Arc<Mutex<HashMap<String, Arc<Mutex<GameTask>>>>>,
+    games: Arc<Games>,
+    config: ServerConfig,
+    sessions: Arc<SessionStore>,
+    /// Level maps loaded from the levels directory, by name
+    levels: HashMap<String, Arc<Level>>,
+    /// Started with the first game, since it must be spawned on the runtime
+    sweeper: OnceLock<JoinHandle<()>>,
 }
 
 impl Default for GameManager {
     fn default() -> Self {
-        Self {
-            games: Arc::new(Mutex::new(HashMap::new())),
-        }
+        Self::with_config(ServerConfig::default())
     }
 }
 
 impl GameManager {
     pub(crate) fn new() -> Self {
+        Self::with_config(ServerConfig::default())
+    }
+
+    pub(crate) fn with_config(config: ServerConfig) -> Self {
+        let levels = load_levels(&config.levels_dir);
         Self {
             games: Arc::new(Mutex::new(HashMap::new())),
+            config,
+            sessions: Arc::new(SessionStore::default()),
+            levels,
+            sweeper: OnceLock::new(),
         }
     }
 
-    async fn get_game(&self, game_id: &String) -> Option<Arc<Mutex<GameTask>>> {
+    async fn get_game(&self, game_id: &str) -> Option<Arc<Mutex<GameTask>>> {
         let map = self.games.lock().await;
-        map.get(game_id).map(|g| g.clone())
+        map.get(game_id).cloned()
+    }
+
+    /// Sends the command built around a reply sender to the game and waits for its reply.
+    /// `action` describes the command in the error returned when the game loop has exited.
+    async fn send_command<T>(
+        &self,
+        game_id: &str,
+        action: &str,
+        command: impl FnOnce(Responder<Result<T, GameError>>) -> GameCommand,
+    ) -> Result<T, GameError> {
+        match self.get_game(game_id).await {
+            None => Err(GameError::GameNotFound(game_id.to_string())),
+            Some(game) => send_command(&game, action, command).await,
+        }
     }
 
+    /// Resolves a session token to the user it was issued to
+    pub(crate) fn authenticate(&self, session_token: &str) -> Result<Principal, GameError> {
+        self.sessions
+            .verify(session_token)
+            .ok_or(GameError::Unauthenticated)
+    }
+
+    pub(crate) fn session_interceptor(&self) -> SessionInterceptor {
+        SessionInterceptor::new(self.sessions.clone())
+    }
+
+    /// Looks up the level the settings name, sizing the board to the level
+    fn resolve_level(&self, settings: &mut GameSettings) -> Result<Option<Arc<Level>>, GameError> {
+        let Some(name) = &settings.level else {
+            return Ok(None);
+        };
+        let level =
+            self.levels.get(name).cloned().ok_or_else(|| {
+                GameError::InvalidArgument(format!("Level {} doesn't exist", name))
+            })?;
+        settings.width = level.width;
+        settings.height = level.height;
+        Ok(Some(level))
+    }
+
+    /// Creates a game and joins it on behalf of the creator, who becomes the host
     pub(crate) async fn create_game(
         &self,
-        width: i32,
-        height: i32,
-        tick_duration_millis: u64,
-    ) -> String {
-        let game = GameTask::new(width, height, tick_duration_millis);
+        mut settings: GameSettings,
+        host_profile: PlayerProfile,
+    ) -> Result<CreateGameReply, GameError> {
+        let level = self.resolve_level(&mut settings)?;
+        self.config.limits.validate(&settings)?;
+        let host_profile = host_profile.validate()?;
+        self.sweeper.get_or_init(|| self.spawn_sweeper());
         let game_id = Uuid::new_v4().to_string();
-        println!("Creating game {}", game_id);
-        let mut games = self.games.lock().await;
-        games.insert(game_id.clone(), Arc::new(Mutex::new(game)));
-        game_id
+        {
+            let mut games = self.games.lock().await;
+            let max_games = self.config.limits.max_games;
+            if games.len() >= max_games {
+                return Err(GameError::Full { max_games });
+            }
+            let game = GameTask::new(game_id.clone(), settings, level);
+            println!("Creating game {}", game_id);
+            games.insert(game_id.clone(), Arc::new(Mutex::new(game)));
+        }
+
+        self.join_host(game_id, host_profile).await
     }
 
-    pub(crate) async fn join_game(&self, game_id: String) -> Result<JoinGameReply, GameError> {
-        match self.get_game(&game_id).await {
-            None => Err(GameError::InvalidGame),
-            Some(game) => {
-                let game = game.lock().await;
-                let (resp_tx, resp_rx) = oneshot::channel();
-
-                let cmd = GameCommand::JoinGame {
-                    reply_sender: resp_tx,
-                };
-                game.send_command(cmd).await;
-
-                // Await the response
-                match resp_rx.await {
-                    Ok(reply) => {
-                        println!("User {} joined game {}", reply.user_id, game_id);
-                        Ok(reply)
-                    }
-                    Err(err) => {
-                        print!("JoinGameReply internal error: {}", err);
-                        Err(GameError::Internal)
-                    }
+    /// Joins the host to the game they created. If they can't join, the game is removed again
+    /// rather than left without a host, taking up one of the `max_games`.
+    async fn join_host(
+        &self,
+        game_id: String,
+        host_profile: PlayerProfile,
+    ) -> Result<CreateGameReply, GameError> {
+        let host = match self
+            .join_game(game_id.clone(), host_profile, JoinMode::Play, None)
+            .await
+        {
+            Ok(host) => host,
+            Err(error) => {
+                let game = self.games.lock().await.remove(&game_id);
+                if let Some(game) = game {
+                    let _ = game.lock().await.shutdown(None).await;
                 }
+                println!("Removed game {} that its host couldn't join", game_id);
+                return Err(error);
             }
-        }
+        };
+        Ok(CreateGameReply {
+            game_id,
+            host_user_id: host.user_id,
+            session_token: host.session_token,
+        })
     }
 
-    pub(crate) async fn start_game(
+    pub(crate) async fn configure_game(
         &self,
-        game_id: String,
-        user_id: String,
+        principal: Principal,
+        mut settings: GameSettings,
     ) -> Result<(), GameError> {
-        match self.games.lock().await.get(&game_id) {
-            None => Err(GameError::InvalidGame),
+        let level = self.resolve_level(&mut settings)?;
+        self.config.limits.validate(&settings)?;
+        self.send_command(principal.game_id(), "configuring game", |reply_sender| {
+            GameCommand::ConfigureGame {
+                reply_sender,
+                principal: principal.clone(),
+                settings,
+                level,
+            }
+        })
+        .await?;
+        println!(
+            "User {} configured game {}",
+            principal.user_id(),
+            principal.game_id()
+        );
+        Ok(())
+    }
+
+    pub(crate) async fn kick_player(
+        &self,
+        principal: Principal,
+        player_id: String,
+    ) -> Result<(), GameError> {
+        self.send_command(principal.game_id(), "kicking player", |reply_sender| {
+            GameCommand::KickPlayer {
+                reply_sender,
+                principal: principal.clone(),
+                player_id: player_id.clone(),
+            }
+        })
+        .await?;
+        println!(
+            "User {} kicked {} from game {}",
+            principal.user_id(),
+            player_id,
+            principal.game_id()
+        );
+        self.sessions
+            .revoke(&Principal::new(principal.game_id().to_string(), player_id));
+        Ok(())
+    }
+
+    pub(crate) async fn transfer_host(
+        &self,
+        principal: Principal,
+        new_host_id: String,
+    ) -> Result<(), GameError> {
+        self.send_command(principal.game_id(), "transferring host", |reply_sender| {
+            GameCommand::TransferHost {
+                reply_sender,
+                principal: principal.clone(),
+                new_host_id: new_host_id.clone(),
+            }
+        })
+        .await?;
+        println!(
+            "User {} made {} the host of game {}",
+            principal.user_id(),
+            new_host_id,
+            principal.game_id()
+        );
+        Ok(())
+    }
+
+    /// Stops the game and removes it. Only the host can delete it.
+    pub(crate) async fn delete_game(&self, principal: Principal) -> Result<(), GameError> {
+        match self.get_game(principal.game_id()).await {
+            None => Err(GameError::GameNotFound(principal.game_id().to_string())),
             Some(game) => {
-                let game = game.lock().await;
-                println!("User {} is starting game {}", user_id, game_id);
-                let (resp_tx, resp_rx) = oneshot::channel();
-                let cmd = GameCommand::StartGame {
-                    reply_sender: resp_tx,
-                    user_id,
-                };
-                game.send_command(cmd).await;
-
-                // Await the response
-                match resp_rx.await {
-                    Ok(game_error_opt) => match game_error_opt {
-                        Some(game_error) => Err(game_error),
-                        None => Ok(()),
-                    },
-                    Err(err) => {
-                        println!("Start game received error: {}", err);
-                        Err(GameError::Internal)
-                    }
-                }
+                game.lock().await.shutdown(Some(principal.clone())).await?;
+                self.games.lock().await.remove(principal.game_id());
+                self.sessions.revoke_game(principal.game_id());
+                println!(
+                    "User {} deleted game {}",
+                    principal.user_id(),
+                    principal.game_id()
+                );
+                Ok(())
             }
         }
     }
 
-    pub(crate) async fn update_game(
+    /// Periodically removes games that are idle or have been over for too long. The sweeper
+    /// exits once the manager is dropped.
+    fn spawn_sweeper(&self) -> JoinHandle<()> {
+        let games = Arc::downgrade(&self.games);
+        let sessions = self.sessions.clone();
+        let config = self.config.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(config.sweep_interval);
+            loop {
+                interval.tick().await;
+                match Weak::upgrade(&games) {
+                    Some(games) => sweep_games(&games, &sessions, &config).await,
+                    None => break,
+                }
+            }
+        })
+    }
+
+    pub(crate) async fn join_game(
         &self,
         game_id: String,
-        user_id: String,
+        profile: PlayerProfile,
+        mode: JoinMode,
+        team: Option<u32>,
+    ) -> Result<JoinGameReply, GameError> {
+        let profile = profile.validate()?;
+        let display_name = profile.display_name.clone();
+        let mut reply = self
+            .send_command(&game_id, "joining game", |reply_sender| {
+                GameCommand::JoinGame {
+                    reply_sender,
+                    profile,
+                    mode,
+                    team,
+                }
+            })
+            .await?;
+        let role = match mode {
+            JoinMode::Play => "player",
+            JoinMode::Spectate => "spectator",
+        };
+        println!(
+            "User {} joined game {} as {} {}",
+            reply.user_id, game_id, role, display_name
+        );
+        let principal = Principal::new(game_id, reply.user_id.clone());
+        reply.session_token = self.sessions.issue(principal);
+        Ok(reply)
+    }
+
+    pub(crate) async fn leave_game(&self, principal: Principal) -> Result<(), GameError> {
+        self.send_command(principal.game_id(), "leaving game", |reply_sender| {
+            GameCommand::LeaveGame {
+                reply_sender,
+                principal: principal.clone(),
+            }
+        })
+        .await?;
+        println!(
+            "User {} left game {}",
+            principal.user_id(),
+            principal.game_id()
+        );
+        self.sessions.revoke(&principal);
+        Ok(())
+    }
+
+    pub(crate) async fn start_game(&self, principal: Principal) -> Result<(), GameError> {
+        self.send_command(principal.game_id(), "starting game", |reply_sender| {
+            GameCommand::StartGame {
+                reply_sender,
+                principal: principal.clone(),
+            }
+        })
+        .await?;
+        println!(
+            "User {} started game {}",
+            principal.user_id(),
+            principal.game_id()
+        );
+        Ok(())
+    }
+
+    pub(crate) async fn restart_game(&self, principal: Principal) -> Result<(), GameError> {
+        self.send_command(principal.game_id(), "restarting game", |reply_sender| {
+            GameCommand::RestartGame {
+                reply_sender,
+                principal: principal.clone(),
+            }
+        })
+        .await?;
+        println!(
+            "User {} restarted game {}",
+            principal.user_id(),
+            principal.game_id()
+        );
+        Ok(())
+    }
+
+    pub(crate) async fn pause_game(&self, principal: Principal) -> Result<(), GameError> {
+        self.send_command(principal.game_id(), "pausing game", |reply_sender| {
+            GameCommand::PauseGame {
+                reply_sender,
+                principal: principal.clone(),
+            }
+        })
+        .await?;
+        println!(
+            "User {} paused game {}",
+            principal.user_id(),
+            principal.game_id()
+        );
+        Ok(())
+    }
+
+    pub(crate) async fn resume_game(&self, principal: Principal) -> Result<(), GameError> {
+        self.send_command(principal.game_id(), "resuming game", |reply_sender| {
+            GameCommand::ResumeGame {
+                reply_sender,
+                principal: principal.clone(),
+            }
+        })
+        .await?;
+        println!(
+            "User {} resumed game {}",
+            principal.user_id(),
+            principal.game_id()
+        );
+        Ok(())
+    }
+
+    pub(crate) async fn update_game(
+        &self,
+        principal: Principal,
         direction: Direction,
     ) -> Result<GameState, GameError> {
-        let games = self.games.lock().await;
-        match games.get(&game_id) {
-            None => Err(GameError::InvalidGame),
-            Some(game) => {
-                let game = game.lock().await;
-                let (resp_tx, resp_rx) = oneshot::channel();
-
-                let cmd = GameCommand::UpdateGame {
-                    reply_sender: resp_tx,
-                    user_id,
-                    direction,
-                };
-                game.send_command(cmd).await;
-
-                // Await the response
-                match resp_rx.await {
-                    Ok(result) => result,
-                    Err(err) => {
-                        println!("Internal error receiving update game response: {}", err);
-                        Err(GameError::Internal)
-                    }
-                }
+        match self.get_game(principal.game_id()).await {
+            None => Err(GameError::GameNotFound(principal.game_id().to_string())),
+            Some(game) => send_update(&game, principal, direction).await,
+        }
+    }
+
+    pub(crate) async fn game_status(&self, principal: Principal) -> Result<GameState, GameError> {
+        let game_id = principal.game_id().to_string();
+        self.send_command(&game_id, "getting GameState", |reply_sender| {
+            GameCommand::GameStatus {
+                reply_sender,
+                principal,
             }
+        })
+        .await
+    }
+
+    pub(crate) async fn watch_game(
+        &self,
+        principal: Principal,
+    ) -> Result<broadcast::Receiver<GameState>, GameError> {
+        match self.get_game(principal.game_id()).await {
+            None => Err(GameError::GameNotFound(principal.game_id().to_string())),
+            Some(game) => send_watch(&game, principal).await,
         }
     }
 
-    pub(crate) async fn game_status(
+    pub(crate) async fn list_games(
         &self,
-        game_id: String,
-        user_id: String,
-    ) -> Result<GameState, GameError> {
-        let games = self.games.lock().await;
-        match games.get(&game_id) {
-            None => Err(GameError::InvalidGame),
-            Some(game) => {
-                let game = game.lock().await;
-                let (resp_tx, resp_rx) = oneshot::channel();
-
-                let cmd = GameCommand::GameStatus {
-                    reply_sender: resp_tx,
-                    user_id,
-                };
-                game.send_command(cmd).await;
-
-                // Await the response
-                match resp_rx.await {
-                    Ok(result) => result,
-                    Err(err) => {
-                        println!("Internal error getting GameState: {}", err);
-                        Err(GameError::Internal)
-                    }
+        filter: GameListFilter,
+        page_size: usize,
+        page_token: Option<String>,
+    ) -> Result<GameListPage, GameError> {
+        let page_size = match page_size {
+            0 => DEFAULT_PAGE_SIZE,
+            page_size => page_size.min(MAX_PAGE_SIZE),
+        };
+        let after = page_token.as_deref().map(parse_page_token).transpose()?;
+
+        // Release the map before asking each game for its summary
+        let games: Vec<Arc<Mutex<GameTask>>> = self.games.lock().await.values().cloned().collect();
+        let mut summaries = Vec::new();
+        for game in games {
+            if let Some(summary) = send_summary(&game).await {
+                if filter.matches(summary.phase) {
+                    summaries.push(summary);
                 }
             }
         }
+
+        summaries.sort_by(|a, b| page_key(a).cmp(&page_key(b)));
+        if let Some((created_at, game_id)) = after {
+            summaries.retain(|summary| page_key(summary) > (created_at, game_id.as_str()));
+        }
+
+        let next_page_token = match summaries.len() > page_size {
+            true => Some(to_page_token(&summaries[page_size - 1])),
+            false => None,
+        };
+        summaries.truncate(page_size);
+
+        Ok(GameListPage {
+            games: summaries,
+            next_page_token,
+        })
+    }
+
+    pub(crate) async fn play_game(&self, principal: Principal) -> Result<PlaySession, GameError> {
+        match self.get_game(principal.game_id()).await {
+            None => Err(GameError::GameNotFound(principal.game_id().to_string())),
+            Some(game) => {
+                let game_states = send_watch(&game, principal.clone()).await?;
+                println!(
+                    "User {} is playing game {}",
+                    principal.user_id(),
+                    principal.game_id()
+                );
+                Ok(PlaySession {
+                    game_states,
+                    voter: DirectionVoter { principal, game },
+                })
+            }
+        }
+    }
+}
+
+async fn sweep_games(games: &Games, sessions: &SessionStore, config: &ServerConfig) {
+    let candidates: Vec<(String, Arc<Mutex<GameTask>>)> = games
+        .lock()
+        .await
+        .iter()
+        .map(|(game_id, game)| (game_id.clone(), game.clone()))
+        .collect();
+
+    for (game_id, game) in candidates {
+        let is_expired = match send_activity(&game).await {
+            Some(GameActivity {
+                finished_at: Some(finished_at),
+                ..
+            }) => finished_at.elapsed() >= config.finished_game_ttl,
+            Some(GameActivity { last_active, .. }) => last_active.elapsed() >= config.idle_game_ttl,
+            // The game loop has exited, so the game can't be used anymore
+            None => true,
+        };
+
+        if is_expired {
+            games.lock().await.remove(&game_id);
+            let _ = game.lock().await.shutdown(None).await;
+            sessions.revoke_game(&game_id);
+            info!("Removed expired game {}", game_id);
+        }
+    }
+}
+
+async fn send_activity(game: &Mutex<GameTask>) -> Option<GameActivity> {
+    let game = game.lock().await;
+    let (resp_tx, resp_rx) = oneshot::channel();
+
+    let cmd = GameCommand::Activity {
+        reply_sender: resp_tx,
+    };
+    game.send_command(cmd).await;
+
+    resp_rx.await.ok()
+}
+
+async fn send_summary(game: &Mutex<GameTask>) -> Option<GameSummary> {
+    let game = game.lock().await;
+    let (resp_tx, resp_rx) = oneshot::channel();
+
+    let cmd = GameCommand::Summary {
+        reply_sender: resp_tx,
+    };
+    game.send_command(cmd).await;
+
+    // Games that can no longer respond are left out of the list
+    resp_rx.await.ok()
+}
+
+/// Games are listed by creation time, using the id to order games created at the same time
+fn page_key(summary: &GameSummary) -> (SystemTime, &str) {
+    (summary.created_at, summary.game_id.as_str())
+}
+
+/// A page token is the key of the last game on the page, so pages stay consistent when
+/// games are created or removed between requests.
+fn to_page_token(summary: &GameSummary) -> String {
+    let created_at = summary
+        .created_at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}:{}", created_at.as_nanos(), summary.game_id)
+}
+
+fn parse_page_token(page_token: &str) -> Result<(SystemTime, String), GameError> {
+    let invalid_token = || GameError::InvalidArgument(format!("Invalid page token {}", page_token));
+    let (created_at, game_id) = page_token.split_once(':').ok_or_else(invalid_token)?;
+    let created_at: u64 = created_at.parse().map_err(|_| invalid_token())?;
+    Ok((
+        UNIX_EPOCH + Duration::from_nanos(created_at),
+        game_id.to_string(),
+    ))
+}
+
+/// Sends the command built around a reply sender to the game and waits for its reply
+async fn send_command<T>(
+    game: &Mutex<GameTask>,
+    action: &str,
+    command: impl FnOnce(Responder<Result<T, GameError>>) -> GameCommand,
+) -> Result<T, GameError> {
+    let game = game.lock().await;
+    let (resp_tx, resp_rx) = oneshot::channel();
+    game.send_command(command(resp_tx)).await;
+
+    match resp_rx.await {
+        Ok(result) => result,
+        Err(err) => {
+            println!("Internal error {}: {}", action, err);
+            Err(GameError::Internal(format!("{}: {}", action, err)))
+        }
+    }
+}
+
+async fn send_update(
+    game: &Mutex<GameTask>,
+    principal: Principal,
+    direction: Direction,
+) -> Result<GameState, GameError> {
+    send_command(game, "updating game", |reply_sender| {
+        GameCommand::UpdateGame {
+            reply_sender,
+            principal,
+            direction,
+        }
+    })
+    .await
+}
+
+async fn send_watch(
+    game: &Mutex<GameTask>,
+    principal: Principal,
+) -> Result<broadcast::Receiver<GameState>, GameError> {
+    send_command(game, "watching game", |reply_sender| {
+        GameCommand::WatchGame {
+            reply_sender,
+            principal,
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::{sync::Mutex, time::timeout};
+
+    use crate::{
+        game_manager::GameManager, game_task::GameTask, session::Principal, types::Direction,
+        GameError, GameLimits, GameListFilter, GameMode, GameSettings, JoinMode, PlayerProfile,
+        ServerConfig,
+    };
+
+    #[tokio::test]
+    async fn play_game_votes() {
+        let manager = GameManager::new();
+        let game = manager
+            .create_game(GameSettings::default(), PlayerProfile::new("Host"))
+            .await
+            .unwrap();
+        let host = manager.authenticate(&game.session_token).unwrap();
+
+        let play_session = manager.play_game(host).await.unwrap();
+        let game_state = play_session.voter.vote(Direction::South).await.unwrap();
+        assert_eq!(game_state.direction, Direction::South);
+    }
+
+    #[tokio::test]
+    async fn play_game_requires_joined_user() {
+        let manager = GameManager::new();
+        let game_id = manager
+            .create_game(GameSettings::default(), PlayerProfile::new("Host"))
+            .await
+            .unwrap()
+            .game_id;
+
+        let unknown_user = Principal::new(game_id, "unknown".to_string());
+        let result = manager.play_game(unknown_user).await;
+        assert!(matches!(result, Err(GameError::UserNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn sessions_end_when_leaving_or_kicked() {
+        let manager = GameManager::new();
+        let game = manager
+            .create_game(GameSettings::default(), PlayerProfile::new("Host"))
+            .await
+            .unwrap();
+        let host = manager.authenticate(&game.session_token).unwrap();
+        assert_eq!(host.game_id(), game.game_id);
+        assert_eq!(host.user_id(), game.host_user_id);
+
+        let player = manager
+            .join_game(
+                game.game_id.clone(),
+                PlayerProfile::new("Player"),
+                JoinMode::Play,
+                None,
+            )
+            .await
+            .unwrap();
+        manager.authenticate(&player.session_token).unwrap();
+        manager
+            .kick_player(host.clone(), player.user_id)
+            .await
+            .unwrap();
+        let result = manager.authenticate(&player.session_token);
+        assert!(matches!(result, Err(GameError::Unauthenticated)));
+
+        manager.leave_game(host).await.unwrap();
+        let result = manager.authenticate(&game.session_token);
+        assert!(matches!(result, Err(GameError::Unauthenticated)));
+    }
+
+    #[tokio::test]
+    async fn join_game_validates_profile() {
+        let manager = GameManager::new();
+        let game_id = manager
+            .create_game(GameSettings::default(), PlayerProfile::new("  Host  "))
+            .await
+            .unwrap()
+            .game_id;
+
+        let result = manager
+            .join_game(
+                game_id.clone(),
+                PlayerProfile::new("host"),
+                JoinMode::Play,
+                None,
+            )
+            .await;
+        assert!(matches!(result, Err(GameError::NameTaken(_))));
+        let result = manager
+            .join_game(
+                game_id.clone(),
+                PlayerProfile::new(" "),
+                JoinMode::Play,
+                None,
+            )
+            .await;
+        assert!(matches!(result, Err(GameError::InvalidArgument(_))));
+        let profile = PlayerProfile {
+            color: Some("red".to_string()),
+            ..PlayerProfile::new("Player")
+        };
+        let result = manager
+            .join_game(game_id, profile, JoinMode::Play, None)
+            .await;
+        assert!(matches!(result, Err(GameError::InvalidArgument(_))));
+    }
+
+    #[tokio::test]
+    async fn list_games_filter() {
+        let manager = GameManager::new();
+        let lobby_game_id = manager
+            .create_game(GameSettings::default(), PlayerProfile::new("Host"))
+            .await
+            .unwrap()
+            .game_id;
+        let running_game = manager
+            .create_game(
+                GameSettings {
+                    width: 20,
+                    tick_duration_millis: 250,
+                    ..GameSettings::default()
+                },
+                PlayerProfile::new("Host"),
+            )
+            .await
+            .unwrap();
+        let running_game_id = running_game.game_id;
+        let host = manager.authenticate(&running_game.session_token).unwrap();
+        manager.start_game(host).await.unwrap();
+
+        let joinable = manager
+            .list_games(GameListFilter::Joinable, 0, None)
+            .await
+            .unwrap();
+        assert_eq!(joinable.games.len(), 1);
+        assert_eq!(joinable.games[0].game_id, lobby_game_id);
+
+        let running = manager
+            .list_games(GameListFilter::Running, 0, None)
+            .await
+            .unwrap();
+        assert_eq!(running.games.len(), 1);
+        assert_eq!(running.games[0].game_id, running_game_id);
+        assert_eq!(running.games[0].width, 20);
+        assert_eq!(running.games[0].tick_duration_millis, 250);
+        assert_eq!(running.games[0].num_users, 1);
+    }
+
+    #[tokio::test]
+    async fn list_games_pages() {
+        let manager = GameManager::new();
+        let mut game_ids = Vec::new();
+        for _ in 0..5 {
+            let game = manager
+                .create_game(GameSettings::default(), PlayerProfile::new("Host"))
+                .await
+                .unwrap();
+            game_ids.push(game.game_id);
+        }
+
+        let mut listed_game_ids = Vec::new();
+        let mut page_token = None;
+        loop {
+            let page = manager
+                .list_games(GameListFilter::All, 2, page_token)
+                .await
+                .unwrap();
+            assert!(page.games.len() <= 2);
+            listed_game_ids.extend(page.games.into_iter().map(|g| g.game_id));
+            page_token = page.next_page_token;
+            if page_token.is_none() {
+                break;
+            }
+        }
+
+        listed_game_ids.sort();
+        game_ids.sort();
+        assert_eq!(listed_game_ids, game_ids);
+    }
+
+    #[tokio::test]
+    async fn list_games_invalid_page_token() {
+        let manager = GameManager::new();
+        let result = manager
+            .list_games(GameListFilter::All, 0, Some("not-a-token".to_string()))
+            .await;
+        assert!(matches!(result, Err(GameError::InvalidArgument(_))));
+    }
+
+    #[tokio::test]
+    async fn start_game_releases_the_game_map() {
+        let manager = Arc::new(GameManager::new());
+        let game = manager
+            .create_game(GameSettings::default(), PlayerProfile::new("Host"))
+            .await
+            .unwrap();
+        let host = manager.authenticate(&game.session_token).unwrap();
+
+        // A game that is busy must not hold up the other games
+        let busy_game = manager.get_game(&game.game_id).await.unwrap();
+        let busy_game = busy_game.lock().await;
+        let start = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.start_game(host).await }
+        });
+        tokio::task::yield_now().await;
+        let other_game = manager.create_game(GameSettings::default(), PlayerProfile::new("Host"));
+        timeout(Duration::from_secs(1), other_game)
+            .await
+            .unwrap()
+            .unwrap();
+
+        drop(busy_game);
+        start.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn delete_game() {
+        let manager = GameManager::new();
+        let game = manager
+            .create_game(GameSettings::default(), PlayerProfile::new("Host"))
+            .await
+            .unwrap();
+        let game_id = game.game_id;
+        let host = manager.authenticate(&game.session_token).unwrap();
+        let player_token = manager
+            .join_game(
+                game_id.clone(),
+                PlayerProfile::new("Player"),
+                JoinMode::Play,
+                None,
+            )
+            .await
+            .unwrap()
+            .session_token;
+        let player = manager.authenticate(&player_token).unwrap();
+
+        let unknown_user = Principal::new(game_id.clone(), "unknown".to_string());
+        let result = manager.delete_game(unknown_user).await;
+        assert!(matches!(result, Err(GameError::UserNotFound(_))));
+        let result = manager.delete_game(player).await;
+        assert!(matches!(result, Err(GameError::PermissionDenied)));
+
+        manager.delete_game(host).await.unwrap();
+        let result = manager
+            .join_game(game_id, PlayerProfile::new("Player"), JoinMode::Play, None)
+            .await;
+        assert!(matches!(result, Err(GameError::GameNotFound(_))));
+        // Deleting the game ends every session in it
+        let result = manager.authenticate(&game.session_token);
+        assert!(matches!(result, Err(GameError::Unauthenticated)));
+        let result = manager.authenticate(&player_token);
+        assert!(matches!(result, Err(GameError::Unauthenticated)));
+    }
+
+    #[tokio::test]
+    async fn game_is_removed_when_host_cannot_join() {
+        let manager = GameManager::new();
+        // The loop of a shut down game no longer answers, so joining it fails
+        let mut game = GameTask::new("game".to_string(), GameSettings::default(), None);
+        game.shutdown(None).await.unwrap();
+        manager
+            .games
+            .lock()
+            .await
+            .insert("game".to_string(), Arc::new(Mutex::new(game)));
+
+        let result = manager
+            .join_host("game".to_string(), PlayerProfile::new("Host"))
+            .await;
+        assert!(matches!(result, Err(GameError::Internal(_))));
+        assert!(manager.games.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn create_game_from_level() {
+        let manager = GameManager::with_config(ServerConfig {
+            levels_dir: Path::new(env!("CARGO_MANIFEST_DIR")).join("levels"),
+            ..ServerConfig::default()
+        });
+        let settings = GameSettings {
+            level: Some("box".to_string()),
+            ..GameSettings::default()
+        };
+        let game = manager
+            .create_game(settings, PlayerProfile::new("Host"))
+            .await
+            .unwrap();
+        let host = manager.authenticate(&game.session_token).unwrap();
+        let game_state = manager.game_status(host).await.unwrap();
+        assert_eq!((game_state.width, game_state.height), (20, 12));
+        assert_eq!(game_state.walls.len(), 2 * 20 + 2 * 10);
+
+        let settings = GameSettings {
+            level: Some("missing".to_string()),
+            ..GameSettings::default()
+        };
+        let result = manager
+            .create_game(settings, PlayerProfile::new("Host"))
+            .await;
+        assert!(matches!(result, Err(GameError::InvalidArgument(_))));
+
+        let settings = GameSettings {
+            level: Some("box".to_string()),
+            game_mode: GameMode::Arena,
+            ..GameSettings::default()
+        };
+        let result = manager
+            .create_game(settings, PlayerProfile::new("Host"))
+            .await;
+        let Err(GameError::InvalidArgument(message)) = result else {
+            panic!("Expected a level to be rejected in arena mode");
+        };
+        assert!(message.contains("level can't be used in arena mode"));
+    }
+
+    #[tokio::test]
+    async fn create_game_validates_settings() {
+        let manager = GameManager::new();
+        let result = manager
+            .create_game(
+                GameSettings {
+                    width: 2,
+                    tick_duration_millis: 0,
+                    start_countdown: Duration::from_secs(61),
+                    food_count: 0,
+                    special_food_percent: 101,
+                    ..GameSettings::default()
+                },
+                PlayerProfile::new("Host"),
+            )
+            .await;
+        let Err(GameError::InvalidArgument(message)) = result else {
+            panic!("Expected invalid settings to be rejected");
+        };
+        assert!(message.contains("width must be between 6 and 200, got 2"));
+        assert!(message.contains("tick_duration_millis must be between 50 and 10000, got 0"));
+        assert!(message.contains("start_countdown_millis must be at most 60000, got 61000"));
+        assert!(message.contains("food_count must be between 1 and 20, got 0"));
+        assert!(message.contains("special_food_percent must be at most 100, got 101"));
+        assert!(!message.contains("height"));
+    }
+
+    #[tokio::test]
+    async fn create_game_limits_game_count() {
+        let manager = GameManager::with_config(ServerConfig {
+            limits: GameLimits {
+                max_games: 1,
+                ..GameLimits::default()
+            },
+            ..ServerConfig::default()
+        });
+        manager
+            .create_game(GameSettings::default(), PlayerProfile::new("Host"))
+            .await
+            .unwrap();
+
+        let result = manager
+            .create_game(GameSettings::default(), PlayerProfile::new("Host"))
+            .await;
+        assert!(matches!(result, Err(GameError::Full { max_games: 1 })));
+    }
+
+    #[tokio::test]
+    async fn sweep_idle_games() {
+        let manager = GameManager::with_config(ServerConfig {
+            idle_game_ttl: Duration::from_millis(50),
+            finished_game_ttl: Duration::from_millis(50),
+            sweep_interval: Duration::from_millis(10),
+            ..ServerConfig::default()
+        });
+        let game_id = manager
+            .create_game(GameSettings::default(), PlayerProfile::new("Host"))
+            .await
+            .unwrap()
+            .game_id;
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let result = manager
+            .join_game(game_id, PlayerProfile::new("Player"), JoinMode::Play, None)
+            .await;
+        assert!(matches!(result, Err(GameError::GameNotFound(_))));
     }
 }
@@ -1,90 +1,331 @@
 use log::{info, warn};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use std::{sync::Arc, sync::Weak};
 
 use tokio::{
+    sync::broadcast,
     sync::mpsc::{self, Sender},
+    sync::oneshot,
     task::JoinHandle,
-    time::{self},
+    time::{self, Instant},
 };
 use uuid::Uuid;
 
-use crate::{game::Game, types::Direction, GameState, Responder};
-use crate::{GameError, JoinGameReply};
+use crate::{game::Game, level::Level, types::Direction, GamePhase, GameState, Responder};
+use crate::{
+    session::Principal, EmptyGamePolicy, GameError, GameSettings, GameSummary, JoinGameReply,
+    JoinMode, PlayerProfile,
+};
+
+/// Number of ticks a watcher can fall behind before it starts missing game states. Every
+/// game state is the whole game, so a watcher that misses some is up to date again with the
+/// next one it reads.
+const WATCH_CHANNEL_CAPACITY: usize = 32;
 
 pub(crate) struct GameTask {
-    _manager: JoinHandle<()>,
+    manager: JoinHandle<()>,
     sender: Arc<Sender<GameCommand>>,
 }
 
+/// When the game was last used, for evicting abandoned games
+pub(crate) struct GameActivity {
+    pub last_active: Instant,
+    pub finished_at: Option<Instant>,
+}
+
 impl GameTask {
-    pub fn new(width: i32, height: i32, tick_duration_millis: u64) -> Self {
+    /// Hosts a game with the given settings, laid out from the level the settings name
+    pub fn new(game_id: String, settings: GameSettings, level: Option<Arc<Level>>) -> Self {
+        let created_at = SystemTime::now();
         let (tx, mut rx) = mpsc::channel::<GameCommand>(32);
         let sender = Arc::new(tx);
         let weak_game_sender = Arc::downgrade(&sender);
 
         // The `move` keyword is used to **move** ownership of `rx` into the task.
-        let _manager = tokio::spawn(async move {
+        let manager = tokio::spawn(async move {
             let game_sender = weak_game_sender;
-            let max_spaces: usize = (width * height).try_into().unwrap();
-            let mut game = Game::new(height, width);
-            let mut _tick_handle = None;
+            let mut settings = settings;
+            let mut game = Game::new(&settings, level);
+            let mut tick_handle: Option<JoinHandle<()>> = None;
+            // Dropped when the game is over so that watchers know the last state was sent
+            let (watch_sender, _) = broadcast::channel::<GameState>(WATCH_CHANNEL_CAPACITY);
+            let mut watch_sender = Some(watch_sender);
+            let mut last_active = Instant::now();
+            let mut finished_at = None;
             // Start receiving messages
             while let Some(cmd) = rx.recv().await {
                 use GameCommand::*;
 
+                if cmd.is_from_user() {
+                    last_active = Instant::now();
+                }
+                let is_tick = matches!(cmd, Tick {});
+                let phase_before = game.phase();
+
                 match cmd {
                     GameStatus {
                         reply_sender,
-                        user_id,
+                        principal,
                     } => {
-                        GameTask::game_status(reply_sender, user_id, &mut game).await;
+                        GameTask::game_status(reply_sender, principal.into_user_id(), &mut game)
+                            .await;
                     }
                     UpdateGame {
                         reply_sender,
-                        user_id,
+                        principal,
                         direction,
                     } => {
-                        GameTask::update_game(reply_sender, user_id, direction, &mut game).await;
+                        GameTask::update_game(
+                            reply_sender,
+                            principal.into_user_id(),
+                            direction,
+                            &mut game,
+                        )
+                        .await;
+                    }
+                    JoinGame {
+                        reply_sender,
+                        profile,
+                        mode,
+                        team,
+                    } => {
+                        let reply = GameTask::join_game(profile, mode, team, &mut game).await;
+                        let _ = reply_sender.send(reply);
+                    }
+                    LeaveGame {
+                        reply_sender,
+                        principal,
+                    } => {
+                        let reply = GameTask::leave_game(
+                            principal.into_user_id(),
+                            settings.empty_game_policy,
+                            &mut game,
+                        )
+                        .await;
+                        let _ = reply_sender.send(reply);
+                    }
+                    KickPlayer {
+                        reply_sender,
+                        principal,
+                        player_id,
+                    } => {
+                        let reply = GameTask::kick_player(
+                            principal.into_user_id(),
+                            player_id,
+                            settings.empty_game_policy,
+                            &mut game,
+                        )
+                        .await;
+                        let _ = reply_sender.send(reply);
                     }
-                    JoinGame { reply_sender } => {
-                        GameTask::join_game(reply_sender, &mut game).await;
+                    TransferHost {
+                        reply_sender,
+                        principal,
+                        new_host_id,
+                    } => {
+                        let reply = GameTask::authorize_host(principal.user_id(), &game)
+                            .and_then(|_| game.transfer_host(&new_host_id));
+                        let _ = reply_sender.send(reply);
+                    }
+                    ConfigureGame {
+                        reply_sender,
+                        principal,
+                        settings: new_settings,
+                        level,
+                    } => {
+                        let reply = GameTask::configure_game(
+                            principal.into_user_id(),
+                            &new_settings,
+                            level,
+                            &mut game,
+                        )
+                        .await;
+                        if reply.is_ok() {
+                            settings = new_settings;
+                        }
+                        let _ = reply_sender.send(reply);
+                    }
+                    Summary { reply_sender } => {
+                        let _ = reply_sender.send(GameSummary {
+                            game_id: game_id.clone(),
+                            width: settings.width,
+                            height: settings.height,
+                            tick_duration_millis: settings.tick_duration_millis,
+                            num_users: game.num_users(),
+                            phase: game.phase(),
+                            created_at,
+                        });
+                    }
+                    WatchGame {
+                        reply_sender,
+                        principal,
+                    } => {
+                        GameTask::watch_game(
+                            reply_sender,
+                            principal.into_user_id(),
+                            watch_sender.as_ref(),
+                            &mut game,
+                        )
+                        .await;
                     }
                     StartGame {
                         reply_sender,
-                        user_id,
+                        principal,
+                    } => {
+                        let reply = GameTask::start_game(
+                            principal.into_user_id(),
+                            &mut game,
+                            settings.start_countdown,
+                            settings.tick_duration_millis,
+                            game_sender.clone(),
+                        )
+                        .await
+                        .map(|handle| tick_handle = Some(handle));
+                        let _ = reply_sender.send(reply);
+                    }
+                    RestartGame {
+                        reply_sender,
+                        principal,
+                    } => {
+                        let reply =
+                            GameTask::restart_game(principal.into_user_id(), &mut game).await;
+                        if reply.is_ok() {
+                            // The new round gets its own watchers, the finished round's
+                            // streams have already ended
+                            watch_sender.get_or_insert_with(|| {
+                                broadcast::channel(WATCH_CHANNEL_CAPACITY).0
+                            });
+                            finished_at = None;
+                        }
+                        let _ = reply_sender.send(reply);
+                    }
+                    PauseGame {
+                        reply_sender,
+                        principal,
+                    } => {
+                        let _ = reply_sender
+                            .send(GameTask::pause_game(principal.into_user_id(), &mut game).await);
+                    }
+                    ResumeGame {
+                        reply_sender,
+                        principal,
                     } => {
-                        let reply = match GameTask::start_game(
-                            user_id,
+                        let reply = match GameTask::resume_game(
+                            principal.into_user_id(),
                             &mut game,
-                            tick_duration_millis,
+                            settings.resume_countdown,
+                            settings.tick_duration_millis,
                             game_sender.clone(),
                         )
                         .await
                         {
-                            Ok(tick_handle) => {
-                                _tick_handle = Some(tick_handle);
-                                None
+                            Ok(handle) => {
+                                tick_handle = Some(handle);
+                                Ok(())
                             }
-                            Err(err) => Some(err),
+                            Err(err) => Err(err),
                         };
-                        reply_sender
-                            .send(reply)
-                            .expect("Start Game response should succeed");
+                        let _ = reply_sender.send(reply);
                     }
                     Tick {} => {
+                        let base_millis = settings.tick_duration_millis;
+                        let tick_duration_before = game.tick_duration_millis(base_millis);
+                        let max_spaces = game.max_spaces();
                         let game_state = GameTask::tick(&mut game, max_spaces).await;
-                        let game_over = game_state.game_over_reason.is_some();
-                        if game_over {
-                            break;
+                        // Speed effects from special food change how often the game ticks
+                        let tick_duration = game.tick_duration_millis(base_millis);
+                        if tick_duration != tick_duration_before {
+                            if let Some(handle) = tick_handle.take() {
+                                handle.abort();
+                                tick_handle = Some(GameTask::spawn_ticker(
+                                    Duration::from_millis(tick_duration),
+                                    tick_duration,
+                                    game_sender.clone(),
+                                ));
+                            }
+                        }
+                        // Watchers receive every tick they keep up with, including the final
+                        // game over state. Sending only fails when there are no watchers.
+                        if let (Some(watch_sender), Some(game_state)) = (&watch_sender, game_state)
+                        {
+                            let _ = watch_sender.send(game_state);
+                        }
+                    }
+                    Activity { reply_sender } => {
+                        // Games with watchers are in use even when nobody sends commands
+                        let is_watched = watch_sender
+                            .as_ref()
+                            .is_some_and(|sender| sender.receiver_count() > 0);
+                        if is_watched {
+                            last_active = Instant::now();
+                        }
+                        let _ = reply_sender.send(GameActivity {
+                            last_active,
+                            finished_at,
+                        });
+                    }
+                    Shutdown {
+                        reply_sender,
+                        principal,
+                    } => {
+                        if let Some(principal) = principal {
+                            if let Err(err) = GameTask::authorize_host(principal.user_id(), &game) {
+                                let _ = reply_sender.send(Err(err));
+                                continue;
+                            }
+                        }
+                        if let Some(handle) = tick_handle.take() {
+                            handle.abort();
                         }
+                        let _ = reply_sender.send(Ok(()));
+                        break;
+                    }
+                }
+
+                // Keep the tick loop and watchers in line with the phase the command left the
+                // game in
+                let phase = game.phase();
+                if !matches!(phase, GamePhase::Countdown | GamePhase::Running) {
+                    if let Some(handle) = tick_handle.take() {
+                        handle.abort();
                     }
                 }
+                if phase != phase_before && !is_tick {
+                    if let Some(watch_sender) = &watch_sender {
+                        let _ = watch_sender.send(game.to_game_state().await);
+                    }
+                }
+                if phase == GamePhase::Finished && finished_at.is_none() {
+                    finished_at = Some(Instant::now());
+                    // Dropping the sender ends the watchers' streams after the final game state
+                    watch_sender = None;
+                }
             }
             warn!("Exiting game loop");
         });
 
-        Self { _manager, sender }
+        Self { manager, sender }
+    }
+
+    /// Stops the tick loop and the game loop, waiting for the game loop to exit.
+    /// When a user is given, only the host can stop it.
+    pub async fn shutdown(&mut self, principal: Option<Principal>) -> Result<(), GameError> {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::Shutdown {
+            reply_sender: resp_tx,
+            principal,
+        };
+        self.send_command(cmd).await;
+
+        match resp_rx.await {
+            Ok(Ok(())) => {
+                let _ = (&mut self.manager).await;
+                Ok(())
+            }
+            Ok(Err(err)) => Err(err),
+            // The game loop has already exited
+            Err(_) => Ok(()),
+        }
     }
 
     pub async fn send_command(&self, command: GameCommand) {
@@ -98,10 +339,11 @@ impl GameTask {
         user_id: String,
         game: &mut Game,
     ) {
-        if game.user_has_joined_game(user_id).await {
-            let _ = reply_sender.send(Ok(game.into_game_state().await));
+        // Polling the status, voting and watching all count as heartbeats
+        if game.heartbeat(&user_id) {
+            let _ = reply_sender.send(Ok(game.to_game_state().await));
         } else {
-            let _ = reply_sender.send(Err(GameError::InvalidUser));
+            let _ = reply_sender.send(Err(GameError::UserNotFound(user_id)));
         }
     }
 
@@ -111,92 +353,334 @@ impl GameTask {
         direction: Direction,
         game: &mut Game,
     ) {
-        if !game.user_has_joined_game(user_id.clone()).await {
-            let _ = reply_sender.send(Err(GameError::InvalidUser));
+        if !game.heartbeat(&user_id) {
+            let _ = reply_sender.send(Err(GameError::UserNotFound(user_id)));
+            return;
+        }
+        if game.is_spectator(&user_id) {
+            let _ = reply_sender.send(Err(GameError::Spectating));
             return;
         }
         game.add_user_direction(user_id, direction).await;
 
-        let game_state = game.into_game_state().await;
+        let game_state = game.to_game_state().await;
         let _ = reply_sender.send(Ok(game_state));
     }
 
-    async fn join_game(join_game_reply_receiver: Responder<JoinGameReply>, game: &mut Game) {
+    async fn watch_game(
+        reply_sender: Responder<Result<broadcast::Receiver<GameState>, GameError>>,
+        user_id: String,
+        watch_sender: Option<&broadcast::Sender<GameState>>,
+        game: &mut Game,
+    ) {
+        if !game.heartbeat(&user_id) {
+            let _ = reply_sender.send(Err(GameError::UserNotFound(user_id)));
+            return;
+        }
+        match watch_sender {
+            Some(watch_sender) => {
+                let _ = reply_sender.send(Ok(watch_sender.subscribe()));
+            }
+            None => {
+                let _ = reply_sender.send(Err(GameError::GameOver));
+            }
+        }
+    }
+
+    async fn join_game(
+        profile: PlayerProfile,
+        mode: JoinMode,
+        team: Option<u32>,
+        game: &mut Game,
+    ) -> Result<JoinGameReply, GameError> {
+        // Checked first so that a player can't be left in the game without their team
+        if let Some(team) = team {
+            game.check_team(team, mode)?;
+        }
         let user_id = Uuid::new_v4().to_string();
-        let _user_is_added = game.add_user(user_id.clone()).await;
+        game.add_user(user_id.clone(), profile, mode).await?;
+        if let Some(team) = team {
+            game.join_team(&user_id, team).await;
+        }
         let (width, height) = game.get_dimensions();
 
-        // Ignore errors
-        let _ = join_game_reply_receiver.send(JoinGameReply {
+        Ok(JoinGameReply {
             user_id,
             width: width as i32,
             height: height as i32,
-        });
+            // Issued by the game manager, which owns the sessions
+            session_token: String::new(),
+        })
+    }
+
+    async fn leave_game(
+        user_id: String,
+        empty_game_policy: EmptyGamePolicy,
+        game: &mut Game,
+    ) -> Result<(), GameError> {
+        if !GameTask::remove_player(&user_id, empty_game_policy, game).await {
+            return Err(GameError::UserNotFound(user_id));
+        }
+        Ok(())
+    }
+
+    async fn kick_player(
+        user_id: String,
+        player_id: String,
+        empty_game_policy: EmptyGamePolicy,
+        game: &mut Game,
+    ) -> Result<(), GameError> {
+        GameTask::authorize_host(&user_id, game)?;
+        if player_id == user_id {
+            return Err(GameError::InvalidArgument(
+                "The host can't kick themselves. Leave the game instead.".to_string(),
+            ));
+        }
+        if !GameTask::remove_player(&player_id, empty_game_policy, game).await {
+            return Err(GameError::InvalidArgument(format!(
+                "User {} has not joined the game",
+                player_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Removes the player, applying the empty game policy if they were the last one.
+    /// Returns false if the player had not joined.
+    async fn remove_player(
+        player_id: &str,
+        empty_game_policy: EmptyGamePolicy,
+        game: &mut Game,
+    ) -> bool {
+        if !game.remove_user(player_id).await {
+            return false;
+        }
+
+        let is_started = matches!(game.phase(), GamePhase::Countdown | GamePhase::Running);
+        if is_started && game.num_users() == 0 {
+            match empty_game_policy {
+                EmptyGamePolicy::End => game.abandon(),
+                EmptyGamePolicy::Pause => {
+                    let _ = game.transition_to(GamePhase::Paused);
+                }
+            }
+        }
+        true
+    }
+
+    /// Only the host can start, configure and moderate the game
+    fn authorize_host(user_id: &str, game: &Game) -> Result<(), GameError> {
+        if !game.user_has_joined(user_id) {
+            return Err(GameError::UserNotFound(user_id.to_string()));
+        }
+        if !game.is_host(user_id) {
+            return Err(GameError::PermissionDenied);
+        }
+        Ok(())
+    }
+
+    async fn configure_game(
+        user_id: String,
+        settings: &GameSettings,
+        level: Option<Arc<Level>>,
+        game: &mut Game,
+    ) -> Result<(), GameError> {
+        GameTask::authorize_host(&user_id, game)?;
+        game.configure(settings, level).await
     }
 
     async fn start_game(
         user_id: String,
         game: &mut Game,
+        start_countdown: Duration,
         tick_duration_millis: u64,
         command_sender: Weak<Sender<GameCommand>>,
     ) -> Result<JoinHandle<()>, GameError> {
-        if game.user_has_joined_game(user_id).await {
-            let _tick = tokio::spawn(async move {
-                let mut interval = time::interval(Duration::from_millis(tick_duration_millis));
-                // Sleep On initial start to allow users time to react after starting game
-                tokio::time::sleep(Duration::from_secs(3)).await;
-                loop {
-                    interval.tick().await;
-                    if let Some(tick_sender) = command_sender.upgrade() {
-                        match tick_sender.send(GameCommand::Tick {}).await {
-                            Ok(_) => info!("Tick!"),
-                            Err(_) => {
-                                warn!("Failed to send tick. Channel Closed");
-                                break;
-                            }
+        GameTask::authorize_host(&user_id, game)?;
+        // Only a game in the lobby can start, so there is never more than one tick loop.
+        // Paused games are resumed instead.
+        if game.phase() == GamePhase::Paused {
+            return Err(GameError::AlreadyStarted);
+        }
+        game.start_countdown(start_countdown)?;
+        Ok(GameTask::spawn_ticker(
+            start_countdown,
+            tick_duration_millis,
+            command_sender,
+        ))
+    }
+
+    async fn restart_game(user_id: String, game: &mut Game) -> Result<(), GameError> {
+        GameTask::authorize_host(&user_id, game)?;
+        game.restart().await
+    }
+
+    /// Stops the clock. The tick loop is aborted once the game is paused.
+    async fn pause_game(user_id: String, game: &mut Game) -> Result<(), GameError> {
+        GameTask::authorize_host(&user_id, game)?;
+        game.transition_to(GamePhase::Paused)
+    }
+
+    async fn resume_game(
+        user_id: String,
+        game: &mut Game,
+        resume_countdown: Duration,
+        tick_duration_millis: u64,
+        command_sender: Weak<Sender<GameCommand>>,
+    ) -> Result<JoinHandle<()>, GameError> {
+        GameTask::authorize_host(&user_id, game)?;
+        match game.phase() {
+            GamePhase::Paused => {}
+            GamePhase::Lobby => return Err(GameError::NotStarted),
+            GamePhase::Finished => return Err(GameError::GameOver),
+            GamePhase::Countdown | GamePhase::Running => return Err(GameError::AlreadyStarted),
+        }
+        game.start_countdown(resume_countdown)?;
+        // A speed effect from before the pause still applies
+        Ok(GameTask::spawn_ticker(
+            resume_countdown,
+            game.tick_duration_millis(tick_duration_millis),
+            command_sender,
+        ))
+    }
+
+    /// Sends a tick every tick duration once the countdown is over
+    fn spawn_ticker(
+        countdown: Duration,
+        tick_duration_millis: u64,
+        command_sender: Weak<Sender<GameCommand>>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            // Sleep during the countdown to allow users time to react after starting game.
+            // The interval starts afterwards so the ticks missed meanwhile aren't sent in a burst.
+            tokio::time::sleep(countdown).await;
+            let mut interval = time::interval(Duration::from_millis(tick_duration_millis));
+            loop {
+                interval.tick().await;
+                if let Some(tick_sender) = command_sender.upgrade() {
+                    match tick_sender.send(GameCommand::Tick {}).await {
+                        Ok(_) => info!("Tick!"),
+                        Err(_) => {
+                            warn!("Failed to send tick. Channel Closed");
+                            break;
                         }
-                    } else {
-                        warn!("Command sender dropped. Exiting tick loop");
-                        break;
                     }
+                } else {
+                    warn!("Command sender dropped. Exiting tick loop");
+                    break;
                 }
-            });
-            return Ok(_tick);
-        }
-        Err(GameError::InvalidUser)
+            }
+        })
     }
 
-    async fn tick(game: &mut Game, max_spaces: usize) -> GameState {
+    /// Ticks the game, returning its new state. Returns nothing when the game ignored the
+    /// tick because it isn't running, like a tick that was already queued when the game was
+    /// paused.
+    async fn tick(game: &mut Game, max_spaces: usize) -> Option<GameState> {
+        let before = (game.epoch(), game.phase());
         game.tick(max_spaces).await;
-        game.into_game_state().await
+        if (game.epoch(), game.phase()) == before {
+            return None;
+        }
+        Some(game.to_game_state().await)
     }
 }
 
 pub(crate) enum GameCommand {
     UpdateGame {
         reply_sender: Responder<Result<GameState, GameError>>,
-        user_id: String,
+        principal: Principal,
         direction: Direction,
     },
     GameStatus {
         reply_sender: Responder<Result<GameState, GameError>>,
-        user_id: String,
+        principal: Principal,
     },
     JoinGame {
-        reply_sender: Responder<JoinGameReply>,
+        reply_sender: Responder<Result<JoinGameReply, GameError>>,
+        profile: PlayerProfile,
+        mode: JoinMode,
+        /// The team a player chose in team mode
+        team: Option<u32>,
+    },
+    LeaveGame {
+        reply_sender: Responder<Result<(), GameError>>,
+        principal: Principal,
+    },
+    KickPlayer {
+        reply_sender: Responder<Result<(), GameError>>,
+        principal: Principal,
+        player_id: String,
+    },
+    TransferHost {
+        reply_sender: Responder<Result<(), GameError>>,
+        principal: Principal,
+        new_host_id: String,
+    },
+    ConfigureGame {
+        reply_sender: Responder<Result<(), GameError>>,
+        principal: Principal,
+        settings: GameSettings,
+        level: Option<Arc<Level>>,
+    },
+    WatchGame {
+        reply_sender: Responder<Result<broadcast::Receiver<GameState>, GameError>>,
+        principal: Principal,
+    },
+    Summary {
+        reply_sender: Responder<GameSummary>,
     },
     StartGame {
-        reply_sender: Responder<Option<GameError>>,
-        user_id: String,
+        reply_sender: Responder<Result<(), GameError>>,
+        principal: Principal,
+    },
+    RestartGame {
+        reply_sender: Responder<Result<(), GameError>>,
+        principal: Principal,
+    },
+    PauseGame {
+        reply_sender: Responder<Result<(), GameError>>,
+        principal: Principal,
+    },
+    ResumeGame {
+        reply_sender: Responder<Result<(), GameError>>,
+        principal: Principal,
+    },
+    Activity {
+        reply_sender: Responder<GameActivity>,
+    },
+    Shutdown {
+        reply_sender: Responder<Result<(), GameError>>,
+        principal: Option<Principal>,
     },
     Tick {},
 }
 
+impl GameCommand {
+    /// Commands sent on behalf of a user, as opposed to the server's own bookkeeping
+    fn is_from_user(&self) -> bool {
+        !matches!(
+            self,
+            GameCommand::Summary { .. }
+                | GameCommand::Activity { .. }
+                | GameCommand::Shutdown { .. }
+                | GameCommand::Tick {}
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::game_task::GameState;
     use crate::output::print_world;
-    use crate::Point;
+    use crate::session::Principal;
+    use crate::{
+        BoardMode, EmptyGamePolicy, FoodItem, GameError, GameMode, GameOverReason, GamePhase,
+        GameSettings, JoinMode, PlayerProfile, Point, SeriesScore,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::sync::broadcast::error::TryRecvError;
     use tokio::sync::oneshot::{self};
 
     use crate::{
@@ -219,12 +703,16 @@ mod tests {
     async fn update_game_command() {
         let game_task = get_test_game();
         let user_id = join_game(&game_task).await;
+        let roster = game_status(&game_task, user_id.clone()).await.roster;
+        assert_eq!(roster.len(), 1);
+        assert_eq!(roster[0].user_id, user_id.user_id());
+        assert!(roster[0].is_host);
 
         let (resp, resp_rx) = oneshot::channel();
         // Send the create game request
         let cmd = GameCommand::UpdateGame {
             reply_sender: resp,
-            user_id,
+            principal: user_id,
             direction: Direction::South,
         };
 
@@ -237,8 +725,23 @@ mod tests {
         let expected_game_state = GameState {
             tick: 0,
             game_over_reason: None,
+            phase: GamePhase::Lobby,
+            board_mode: BoardMode::Bounded,
+            walls: Vec::new(),
+            countdown_remaining: None,
+            first_tick_at: None,
             direction: Direction::South,
             num_users: 1,
+            num_active: 1,
+            num_idle: 0,
+            num_spectators: 0,
+            score: 0,
+            series: SeriesScore {
+                round: 1,
+                total_score: 0,
+                best_score: 0,
+            },
+            roster,
             body: vec![
                 Point::new(2, HEIGHT / 2),
                 Point::new(1, HEIGHT / 2),
@@ -246,7 +749,12 @@ mod tests {
             ],
             height: HEIGHT,
             width: HEIGHT,
-            food: Point::new(0, 0),
+            food: vec![FoodItem::regular(Point::new(HEIGHT / 2, HEIGHT / 2))],
+            speed_effect: None,
+            game_mode: GameMode::CrowdVote,
+            snakes: Vec::new(),
+            winner: None,
+            seed: 7,
         };
         println!("Actual:");
         print_world(&game_state);
@@ -255,20 +763,532 @@ mod tests {
         assert_eq!(game_state, expected_game_state);
     }
 
+    #[tokio::test]
+    async fn spectator_watches_without_voting() {
+        let game_task = get_test_game();
+        let host = join_game(&game_task).await;
+        let spectator = join_game_as(&game_task, JoinMode::Spectate).await;
+
+        let game_state = game_status(&game_task, spectator.clone()).await;
+        assert_eq!(game_state.num_users, 1);
+        assert_eq!(game_state.num_spectators, 1);
+        let spectator_entry = game_state
+            .roster
+            .iter()
+            .find(|entry| entry.user_id == spectator.user_id())
+            .unwrap();
+        assert!(spectator_entry.is_spectator);
+        assert!(!spectator_entry.is_host);
+
+        start_game(&game_task, host).await.unwrap();
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::WatchGame {
+            reply_sender: resp,
+            principal: spectator.clone(),
+        };
+        game_task.send_command(cmd).await;
+        assert!(resp_rx.await.unwrap().is_ok());
+
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::UpdateGame {
+            reply_sender: resp,
+            principal: spectator,
+            direction: Direction::South,
+        };
+        game_task.send_command(cmd).await;
+        assert!(matches!(resp_rx.await.unwrap(), Err(GameError::Spectating)));
+    }
+
+    #[tokio::test]
+    async fn watch_game_command() {
+        let game_task = get_test_game();
+        let user_id = join_game(&game_task).await;
+        start_game(&game_task, user_id.clone()).await.unwrap();
+
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::WatchGame {
+            reply_sender: resp,
+            principal: user_id.clone(),
+        };
+        game_task.send_command(cmd).await;
+        let mut receiver = resp_rx.await.unwrap().unwrap();
+
+        // Rusty starts at x = 2 heading east and leaves the 10x10 board on the 8th tick
+        for _ in 0..8 {
+            game_task.send_command(GameCommand::Tick {}).await;
+        }
+
+        for expected_tick in 1..8 {
+            let game_state = receiver.recv().await.unwrap();
+            assert_eq!(game_state.tick, expected_tick);
+            assert_eq!(game_state.phase, GamePhase::Running);
+            assert_eq!(game_state.game_over_reason, None);
+        }
+
+        let final_game_state = receiver.recv().await.unwrap();
+        assert_eq!(final_game_state.tick, 8);
+        assert_eq!(final_game_state.phase, GamePhase::Finished);
+        assert_eq!(
+            final_game_state.game_over_reason,
+            Some(GameOverReason::OutOfBounds)
+        );
+        assert!(receiver.recv().await.is_err());
+
+        // The finished game can still be queried
+        let game_state = game_status(&game_task, user_id).await;
+        assert_eq!(game_state.phase, GamePhase::Finished);
+    }
+
+    #[tokio::test]
+    async fn shutdown_game() {
+        let mut game_task = get_test_game();
+        let user_id = join_game(&game_task).await;
+
+        let result = game_task.shutdown(Some(unknown_user())).await;
+        assert!(matches!(result, Err(GameError::UserNotFound(_))));
+
+        game_task.shutdown(Some(user_id)).await.unwrap();
+        assert!(game_task.manager.is_finished());
+    }
+
+    #[tokio::test]
+    async fn start_game_command() {
+        let game_task = get_test_game();
+        let user_id = join_game(&game_task).await;
+
+        start_game(&game_task, user_id.clone()).await.unwrap();
+        let game_state = game_status(&game_task, user_id.clone()).await;
+        assert_eq!(game_state.phase, GamePhase::Countdown);
+        assert!(game_state.countdown_remaining.is_some());
+
+        // Starting again must not spawn a second tick loop
+        let error = start_game(&game_task, user_id).await;
+        assert!(matches!(error, Err(GameError::AlreadyStarted)));
+    }
+
+    #[tokio::test]
+    async fn start_game_without_caller() {
+        let game_task = get_test_game();
+        let user_id = join_game(&game_task).await;
+
+        // The caller gave up before the reply was sent
+        let (resp, resp_rx) = oneshot::channel();
+        drop(resp_rx);
+        let cmd = GameCommand::StartGame {
+            reply_sender: resp,
+            principal: user_id.clone(),
+        };
+        game_task.send_command(cmd).await;
+
+        let game_state = game_status(&game_task, user_id).await;
+        assert_eq!(game_state.phase, GamePhase::Countdown);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn start_countdown_is_configurable() {
+        let game_task = get_test_game_with_settings(GameSettings {
+            start_countdown: Duration::from_secs(5),
+            ..test_settings()
+        });
+        let user_id = join_game(&game_task).await;
+
+        start_game(&game_task, user_id.clone()).await.unwrap();
+        let game_state = game_status(&game_task, user_id.clone()).await;
+        assert_eq!(game_state.phase, GamePhase::Countdown);
+        assert_eq!(game_state.countdown_remaining, Some(Duration::from_secs(5)));
+        assert!(game_state.first_tick_at.is_some());
+
+        // The first tick comes when the countdown is over, the second a tick duration later
+        tokio::time::sleep(Duration::from_millis(5500)).await;
+        let game_state = game_status(&game_task, user_id).await;
+        assert_eq!(game_state.phase, GamePhase::Running);
+        assert_eq!(game_state.tick, 1);
+        assert_eq!(game_state.countdown_remaining, None);
+        assert_eq!(game_state.first_tick_at, None);
+    }
+
+    #[tokio::test]
+    async fn tick_ignored_in_lobby() {
+        let game_task = get_test_game();
+        let user_id = join_game(&game_task).await;
+
+        game_task.send_command(GameCommand::Tick {}).await;
+        let game_state = game_status(&game_task, user_id).await;
+        assert_eq!(game_state.phase, GamePhase::Lobby);
+        assert_eq!(game_state.tick, 0);
+    }
+
+    #[tokio::test]
+    async fn leave_game_command() {
+        let game_task = get_test_game();
+        let user_id = join_game(&game_task).await;
+        let other_user_id = join_game(&game_task).await;
+
+        assert!(leave_game(&game_task, user_id.clone()).await.is_ok());
+        let result = leave_game(&game_task, user_id).await;
+        assert!(matches!(result, Err(GameError::UserNotFound(_))));
+
+        let game_state = game_status(&game_task, other_user_id).await;
+        assert_eq!(game_state.phase, GamePhase::Lobby);
+    }
+
+    #[tokio::test]
+    async fn last_player_leaves_ends_game() {
+        let game_task = get_test_game();
+        let user_id = join_game(&game_task).await;
+        let watcher = join_game(&game_task).await;
+        start_game(&game_task, user_id.clone()).await.unwrap();
+        game_task.send_command(GameCommand::Tick {}).await;
+
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::WatchGame {
+            reply_sender: resp,
+            principal: watcher.clone(),
+        };
+        game_task.send_command(cmd).await;
+        let mut receiver = resp_rx.await.unwrap().unwrap();
+
+        leave_game(&game_task, user_id).await.unwrap();
+        leave_game(&game_task, watcher).await.unwrap();
+
+        let final_game_state = receiver.recv().await.unwrap();
+        assert_eq!(final_game_state.phase, GamePhase::Finished);
+        assert_eq!(
+            final_game_state.game_over_reason,
+            Some(GameOverReason::Abandoned)
+        );
+        assert!(receiver.recv().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn last_player_leaves_pauses_game() {
+        let game_task = get_test_game_with_settings(GameSettings {
+            empty_game_policy: EmptyGamePolicy::Pause,
+            ..test_settings()
+        });
+        let user_id = join_game(&game_task).await;
+        start_game(&game_task, user_id.clone()).await.unwrap();
+        game_task.send_command(GameCommand::Tick {}).await;
+        leave_game(&game_task, user_id).await.unwrap();
+
+        // A returning player can resume the paused game
+        let user_id = join_game(&game_task).await;
+        let game_state = game_status(&game_task, user_id.clone()).await;
+        assert_eq!(game_state.phase, GamePhase::Paused);
+        assert!(resume_game(&game_task, user_id.clone()).await.is_ok());
+        let game_state = game_status(&game_task, user_id).await;
+        assert_eq!(game_state.phase, GamePhase::Countdown);
+        assert_eq!(game_state.tick, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_players_are_afk() {
+        let game_task = get_test_game();
+        let user_id = join_game(&game_task).await;
+        let _idle_user_id = join_game(&game_task).await;
+
+        let game_state = game_status(&game_task, user_id.clone()).await;
+        assert_eq!((game_state.num_active, game_state.num_idle), (2, 0));
+
+        tokio::time::advance(test_settings().afk_timeout).await;
+        let game_state = game_status(&game_task, user_id).await;
+        assert_eq!(game_state.num_users, 2);
+        assert_eq!((game_state.num_active, game_state.num_idle), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn ignored_ticks_are_not_watched() {
+        let game_task = get_test_game();
+        let user_id = join_game(&game_task).await;
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::WatchGame {
+            reply_sender: resp,
+            principal: user_id.clone(),
+        };
+        game_task.send_command(cmd).await;
+        let mut receiver = resp_rx.await.unwrap().unwrap();
+
+        game_task.send_command(GameCommand::Tick {}).await;
+        start_game(&game_task, user_id.clone()).await.unwrap();
+        game_task.send_command(GameCommand::Tick {}).await;
+        pause_game(&game_task, user_id.clone()).await.unwrap();
+        // A tick that was queued before the ticker stopped
+        game_task.send_command(GameCommand::Tick {}).await;
+        resume_game(&game_task, user_id.clone()).await.unwrap();
+        game_task.send_command(GameCommand::Tick {}).await;
+        game_status(&game_task, user_id).await;
+
+        // One state per tick played and per phase change, and none for the ignored ticks
+        let expected = [
+            (0, GamePhase::Countdown),
+            (1, GamePhase::Running),
+            (1, GamePhase::Paused),
+            (1, GamePhase::Countdown),
+            (2, GamePhase::Running),
+        ];
+        for expected in expected {
+            let game_state = receiver.recv().await.unwrap();
+            assert_eq!((game_state.tick, game_state.phase), expected);
+        }
+        assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+    }
+
+    #[tokio::test]
+    async fn pause_and_resume_game_command() {
+        let game_task = get_test_game();
+        let user_id = join_game(&game_task).await;
+
+        let result = pause_game(&game_task, user_id.clone()).await;
+        assert!(matches!(result, Err(GameError::NotStarted)));
+        let result = resume_game(&game_task, user_id.clone()).await;
+        assert!(matches!(result, Err(GameError::NotStarted)));
+
+        start_game(&game_task, user_id.clone()).await.unwrap();
+        game_task.send_command(GameCommand::Tick {}).await;
+        pause_game(&game_task, user_id.clone()).await.unwrap();
+
+        // Ticks are ignored while paused
+        game_task.send_command(GameCommand::Tick {}).await;
+        let game_state = game_status(&game_task, user_id.clone()).await;
+        assert_eq!(game_state.phase, GamePhase::Paused);
+        assert_eq!(game_state.tick, 1);
+        let error = start_game(&game_task, user_id.clone()).await;
+        assert!(matches!(error, Err(GameError::AlreadyStarted)));
+
+        resume_game(&game_task, user_id.clone()).await.unwrap();
+        let game_state = game_status(&game_task, user_id.clone()).await;
+        assert_eq!(game_state.phase, GamePhase::Countdown);
+        assert_eq!(game_state.countdown_remaining, Some(Duration::ZERO));
+        let result = resume_game(&game_task, user_id).await;
+        assert!(matches!(result, Err(GameError::AlreadyStarted)));
+    }
+
+    #[tokio::test]
+    async fn restart_game_command() {
+        let game_task = get_test_game();
+        let user_id = join_game(&game_task).await;
+        start_game(&game_task, user_id.clone()).await.unwrap();
+
+        // Rusty starts heading east and runs out of bounds
+        for _ in 0..8 {
+            game_task.send_command(GameCommand::Tick {}).await;
+        }
+        let game_state = game_status(&game_task, user_id.clone()).await;
+        assert_eq!(game_state.phase, GamePhase::Finished);
+
+        restart_game(&game_task, user_id.clone()).await.unwrap();
+        let game_state = game_status(&game_task, user_id.clone()).await;
+        assert_eq!(game_state.phase, GamePhase::Lobby);
+        assert_eq!(game_state.tick, 0);
+        assert_eq!(game_state.series.round, 2);
+
+        // The new round can be watched and played
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::WatchGame {
+            reply_sender: resp,
+            principal: user_id.clone(),
+        };
+        game_task.send_command(cmd).await;
+        let mut receiver = resp_rx.await.unwrap().unwrap();
+        start_game(&game_task, user_id).await.unwrap();
+        assert_eq!(receiver.recv().await.unwrap().phase, GamePhase::Countdown);
+    }
+
+    #[tokio::test]
+    async fn host_only_commands() {
+        let game_task = get_test_game();
+        let host_id = join_game(&game_task).await;
+        let player_id = join_game(&game_task).await;
+
+        let error = start_game(&game_task, player_id.clone()).await;
+        assert!(matches!(error, Err(GameError::PermissionDenied)));
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::KickPlayer {
+            reply_sender: resp,
+            principal: player_id.clone(),
+            player_id: host_id.user_id().to_string(),
+        };
+        game_task.send_command(cmd).await;
+        assert!(matches!(
+            resp_rx.await.unwrap(),
+            Err(GameError::PermissionDenied)
+        ));
+
+        // The new host can configure the game, while the old host can't
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::TransferHost {
+            reply_sender: resp,
+            principal: host_id.clone(),
+            new_host_id: player_id.user_id().to_string(),
+        };
+        game_task.send_command(cmd).await;
+        resp_rx.await.unwrap().unwrap();
+        let settings = GameSettings {
+            width: 6,
+            height: 8,
+            ..test_settings()
+        };
+        let result = configure_game(&game_task, host_id.clone(), settings.clone()).await;
+        assert!(matches!(result, Err(GameError::PermissionDenied)));
+        configure_game(&game_task, player_id.clone(), settings)
+            .await
+            .unwrap();
+        let game_state = game_status(&game_task, host_id.clone()).await;
+        assert_eq!((game_state.width, game_state.height), (6, 8));
+
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::KickPlayer {
+            reply_sender: resp,
+            principal: player_id.clone(),
+            player_id: host_id.user_id().to_string(),
+        };
+        game_task.send_command(cmd).await;
+        resp_rx.await.unwrap().unwrap();
+        let game_state = game_status(&game_task, player_id).await;
+        assert_eq!(game_state.num_users, 1);
+        let result = leave_game(&game_task, host_id).await;
+        assert!(matches!(result, Err(GameError::UserNotFound(_))));
+    }
+
     fn get_test_game() -> GameTask {
-        GameTask::new(10, 10, 1000)
+        get_test_game_with_settings(test_settings())
     }
 
-    async fn join_game(game_task: &GameTask) -> String {
+    fn get_test_game_with_settings(settings: GameSettings) -> GameTask {
+        GameTask::new("test_game_id".to_string(), settings, None)
+    }
+
+    fn test_settings() -> GameSettings {
+        GameSettings {
+            width: 10,
+            height: 10,
+            tick_duration_millis: 1000,
+            empty_game_policy: EmptyGamePolicy::End,
+            board_mode: BoardMode::Bounded,
+            level: None,
+            afk_timeout: Duration::from_secs(30),
+            start_countdown: Duration::from_secs(3),
+            resume_countdown: Duration::ZERO,
+            food_count: 1,
+            special_food_percent: 0,
+            special_food_lifetime: 20,
+            game_mode: GameMode::CrowdVote,
+            team_count: 2,
+            seed: Some(7),
+        }
+    }
+
+    /// A principal for a user that never joined the test game
+    fn unknown_user() -> Principal {
+        Principal::new("test_game_id".to_string(), "unknown".to_string())
+    }
+
+    async fn join_game(game_task: &GameTask) -> Principal {
+        join_game_as(game_task, JoinMode::Play).await
+    }
+
+    /// Joins under a display name that no other test player uses
+    async fn join_game_as(game_task: &GameTask, mode: JoinMode) -> Principal {
+        static PLAYER_NUMBER: AtomicUsize = AtomicUsize::new(1);
+        let player_number = PLAYER_NUMBER.fetch_add(1, Ordering::Relaxed);
+
         let (resp, resp_rx) = oneshot::channel();
         // Send the create game request
-        let cmd = GameCommand::JoinGame { reply_sender: resp };
+        let cmd = GameCommand::JoinGame {
+            reply_sender: resp,
+            profile: PlayerProfile::new(format!("Player {}", player_number)),
+            mode,
+            team: None,
+        };
 
         game_task.send_command(cmd).await;
 
         // Await the response
         let res = resp_rx.await;
-        let response = res.unwrap();
-        response.user_id
+        let response = res.unwrap().unwrap();
+        Principal::new("test_game_id".to_string(), response.user_id)
+    }
+
+    async fn start_game(game_task: &GameTask, principal: Principal) -> Result<(), GameError> {
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::StartGame {
+            reply_sender: resp,
+            principal,
+        };
+
+        game_task.send_command(cmd).await;
+        resp_rx.await.unwrap()
+    }
+
+    async fn configure_game(
+        game_task: &GameTask,
+        principal: Principal,
+        settings: GameSettings,
+    ) -> Result<(), GameError> {
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::ConfigureGame {
+            reply_sender: resp,
+            principal,
+            settings,
+            level: None,
+        };
+
+        game_task.send_command(cmd).await;
+        resp_rx.await.unwrap()
+    }
+
+    async fn restart_game(game_task: &GameTask, principal: Principal) -> Result<(), GameError> {
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::RestartGame {
+            reply_sender: resp,
+            principal,
+        };
+
+        game_task.send_command(cmd).await;
+        resp_rx.await.unwrap()
+    }
+
+    async fn pause_game(game_task: &GameTask, principal: Principal) -> Result<(), GameError> {
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::PauseGame {
+            reply_sender: resp,
+            principal,
+        };
+
+        game_task.send_command(cmd).await;
+        resp_rx.await.unwrap()
+    }
+
+    async fn resume_game(game_task: &GameTask, principal: Principal) -> Result<(), GameError> {
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::ResumeGame {
+            reply_sender: resp,
+            principal,
+        };
+
+        game_task.send_command(cmd).await;
+        resp_rx.await.unwrap()
+    }
+
+    async fn leave_game(game_task: &GameTask, principal: Principal) -> Result<(), GameError> {
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::LeaveGame {
+            reply_sender: resp,
+            principal,
+        };
+
+        game_task.send_command(cmd).await;
+        resp_rx.await.unwrap()
+    }
+
+    async fn game_status(game_task: &GameTask, principal: Principal) -> GameState {
+        let (resp, resp_rx) = oneshot::channel();
+        let cmd = GameCommand::GameStatus {
+            reply_sender: resp,
+            principal,
+        };
+
+        game_task.send_command(cmd).await;
+        resp_rx.await.unwrap().unwrap()
     }
 }
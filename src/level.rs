@@ -0,0 +1,199 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    sync::Arc,
+};
+
+use log::{info, warn};
+
+use crate::{game::STARTING_LENGTH, types::Direction, Point};
+
+/// A board layout loaded from a level map.
+///
+/// Maps are plain text, one line per row of the board:
+///
+/// - `#` is a wall
+/// - `.` or a space is empty
+/// - `*` is the initial food
+/// - `^`, `>`, `v` or `<` is rusty's head, facing north, east, south or west
+///
+/// Rusty's body trails behind the head, so the cells behind it must be empty. Rows shorter
+/// than the longest row are padded with empty cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Level {
+    pub(crate) name: String,
+    pub(crate) width: i32,
+    pub(crate) height: i32,
+    pub(crate) walls: HashSet<Point>,
+    pub(crate) start: Point,
+    pub(crate) start_direction: Direction,
+    pub(crate) food: Point,
+}
+
+impl Level {
+    /// Parses a level map, describing the first problem found when the map isn't valid
+    pub(crate) fn parse(name: &str, map: &str) -> Result<Self, String> {
+        let rows: Vec<&str> = map.trim_end().lines().collect();
+        let height = rows.len() as i32;
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0) as i32;
+        if width == 0 {
+            return Err("the map is empty".to_string());
+        }
+
+        let mut walls = HashSet::new();
+        let mut head = None;
+        let mut food = None;
+        for (y, row) in rows.iter().enumerate() {
+            for (x, cell) in row.chars().enumerate() {
+                let point = Point::new(x as i32, y as i32);
+                let direction = match cell {
+                    '#' => {
+                        walls.insert(point);
+                        continue;
+                    }
+                    '.' | ' ' => continue,
+                    '*' => {
+                        if food.replace(point).is_some() {
+                            return Err("the map has more than one food".to_string());
+                        }
+                        continue;
+                    }
+                    '^' => Direction::North,
+                    '>' => Direction::East,
+                    'v' => Direction::South,
+                    '<' => Direction::West,
+                    other => {
+                        return Err(format!("unknown cell {:?} at ({}, {})", other, x, y));
+                    }
+                };
+                if head.replace((point, direction)).is_some() {
+                    return Err("the map has more than one head".to_string());
+                }
+            }
+        }
+        let (start, start_direction) = head.ok_or("the map has no head")?;
+        let food = food.ok_or("the map has no food")?;
+
+        let mut segment = start;
+        for _ in 1..STARTING_LENGTH {
+            segment = segment.add_direction(&start_direction.opposite());
+            let in_bounds =
+                segment.x >= 0 && segment.y >= 0 && segment.x < width && segment.y < height;
+            if !in_bounds || walls.contains(&segment) || segment == food {
+                return Err(format!(
+                    "rusty's body needs empty cells behind the head, ({}, {}) isn't",
+                    segment.x, segment.y
+                ));
+            }
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            width,
+            height,
+            walls,
+            start,
+            start_direction,
+            food,
+        })
+    }
+}
+
+/// Loads every `<level name>.txt` map in the directory. Maps that can't be read or parsed
+/// are skipped with a warning.
+pub(crate) fn load_levels(dir: &Path) -> HashMap<String, Arc<Level>> {
+    let mut levels = HashMap::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            info!("No levels loaded from {}: {}", dir.display(), err);
+            return levels;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|extension| extension != "txt") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let level = fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|map| Level::parse(name, &map));
+        match level {
+            Ok(level) => {
+                levels.insert(name.to_string(), Arc::new(level));
+            }
+            Err(problem) => warn!("Skipping level {}: {}", path.display(), problem),
+        }
+    }
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::level::{load_levels, Level};
+    use crate::types::Direction;
+    use crate::Point;
+
+    #[test]
+    fn parse_level() {
+        let map = "\
+#######
+#.....#
+#..>..#
+#.....#
+#...*
+#######
+";
+        let level = Level::parse("box", map).unwrap();
+        assert_eq!(level.name, "box");
+        assert_eq!((level.width, level.height), (7, 6));
+        assert_eq!(level.start, Point::new(3, 2));
+        assert_eq!(level.start_direction, Direction::East);
+        assert_eq!(level.food, Point::new(4, 4));
+        assert_eq!(level.walls.len(), 7 + 2 + 2 + 2 + 1 + 7);
+        assert!(level.walls.contains(&Point::new(6, 3)));
+        // The short row is padded with empty cells
+        assert!(!level.walls.contains(&Point::new(6, 4)));
+    }
+
+    #[test]
+    fn parse_invalid_levels() {
+        let problems = [
+            ("", "the map is empty"),
+            ("..*", "the map has no head"),
+            ("..>", "the map has no food"),
+            ("..>\n..>\n*..", "the map has more than one head"),
+            ("..>x*", "unknown cell 'x' at (3, 0)"),
+            (
+                ".#>.*",
+                "rusty's body needs empty cells behind the head, (1, 0) isn't",
+            ),
+            (
+                ".>..*",
+                "rusty's body needs empty cells behind the head, (-1, 0) isn't",
+            ),
+        ];
+        for (map, problem) in problems {
+            assert_eq!(Level::parse("bad", map), Err(problem.to_string()));
+        }
+    }
+
+    #[test]
+    fn load_shipped_levels() {
+        let levels = load_levels(&Path::new(env!("CARGO_MANIFEST_DIR")).join("levels"));
+        assert!(levels.contains_key("box"));
+        assert!(levels.contains_key("pillars"));
+        assert!(load_levels(Path::new("missing-levels")).is_empty());
+    }
+}
@@ -1,14 +1,30 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use game_manager::GameManager;
-use tokio::sync::oneshot;
+pub use game_manager::{DirectionVoter, PlaySession};
+pub use session::{
+    is_well_formed_token, parse_bearer_token, Principal, SessionInterceptor, AUTHORIZATION,
+};
+use tokio::sync::{broadcast, oneshot};
 use types::Direction;
 
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub use game::bench;
+
+mod free_cells;
 mod game;
 mod game_manager;
 mod game_task;
+mod level;
 pub mod output;
 mod requested_direction;
 pub mod service;
+mod session;
 mod types;
+mod vote_limiter;
 
 pub mod proto {
     tonic::include_proto!("rusty");
@@ -33,52 +49,578 @@ impl RustyGame {
         }
     }
 
-    pub async fn create_game(&self, width: i32, height: i32, tick_duration_millis: u64) -> String {
-        self.manager
-            .create_game(width, height, tick_duration_millis)
-            .await
+    pub fn with_config(config: ServerConfig) -> Self {
+        RustyGame {
+            manager: GameManager::with_config(config),
+        }
     }
 
-    pub async fn join_game(&self, game_id: String) -> Result<JoinGameReply, GameError> {
-        self.manager.join_game(game_id).await
+    /// Create a game and join it as the host. Only the host can start, configure and
+    /// moderate the game.
+    pub async fn create_game(
+        &self,
+        settings: GameSettings,
+        host_profile: PlayerProfile,
+    ) -> Result<CreateGameReply, GameError> {
+        self.manager.create_game(settings, host_profile).await
     }
 
-    pub async fn start_game(&self, game_id: String, user_id: String) -> Result<(), GameError> {
-        self.manager.start_game(game_id, user_id).await
+    /// Change the settings of a game that hasn't started. Host only.
+    pub async fn configure_game(
+        &self,
+        principal: Principal,
+        settings: GameSettings,
+    ) -> Result<(), GameError> {
+        self.manager.configure_game(principal, settings).await
     }
 
-    pub async fn game_status(
+    /// Remove another player from the game. Host only.
+    pub async fn kick_player(
+        &self,
+        principal: Principal,
+        player_id: String,
+    ) -> Result<(), GameError> {
+        self.manager.kick_player(principal, player_id).await
+    }
+
+    /// Hand the host role to another player. Host only.
+    pub async fn transfer_host(
+        &self,
+        principal: Principal,
+        new_host_id: String,
+    ) -> Result<(), GameError> {
+        self.manager.transfer_host(principal, new_host_id).await
+    }
+
+    /// Stop the game and remove it from the server. Host only.
+    pub async fn delete_game(&self, principal: Principal) -> Result<(), GameError> {
+        self.manager.delete_game(principal).await
+    }
+
+    /// Resolve the session token handed out by `create_game` or `join_game` to the user
+    /// it was issued to
+    pub fn authenticate(&self, session_token: &str) -> Result<Principal, GameError> {
+        self.manager.authenticate(session_token)
+    }
+
+    /// Interceptor that authenticates gRPC requests for this game server
+    pub fn session_interceptor(&self) -> SessionInterceptor {
+        self.manager.session_interceptor()
+    }
+
+    /// Join the game as the given player or spectator. Display names are unique within a
+    /// game. In team mode, players play for the given team, or the team with the fewest
+    /// players.
+    pub async fn join_game(
         &self,
         game_id: String,
-        user_id: String,
-    ) -> Result<GameState, GameError> {
-        self.manager.game_status(game_id, user_id).await
+        profile: PlayerProfile,
+        mode: JoinMode,
+        team: Option<u32>,
+    ) -> Result<JoinGameReply, GameError> {
+        self.manager.join_game(game_id, profile, mode, team).await
+    }
+
+    /// Remove the user from the game, along with their vote
+    pub async fn leave_game(&self, principal: Principal) -> Result<(), GameError> {
+        self.manager.leave_game(principal).await
+    }
+
+    pub async fn start_game(&self, principal: Principal) -> Result<(), GameError> {
+        self.manager.start_game(principal).await
+    }
+
+    /// Start a new round of a finished game with the same players and settings
+    pub async fn restart_game(&self, principal: Principal) -> Result<(), GameError> {
+        self.manager.restart_game(principal).await
+    }
+
+    /// Stop the clock of a started game
+    pub async fn pause_game(&self, principal: Principal) -> Result<(), GameError> {
+        self.manager.pause_game(principal).await
+    }
+
+    /// Start the clock of a paused game again, after the game's resume countdown
+    pub async fn resume_game(&self, principal: Principal) -> Result<(), GameError> {
+        self.manager.resume_game(principal).await
+    }
+
+    pub async fn game_status(&self, principal: Principal) -> Result<GameState, GameError> {
+        self.manager.game_status(principal).await
     }
 
     pub async fn update_game(
         &self,
-        game_id: String,
-        user_id: String,
+        principal: Principal,
         direction: Direction,
     ) -> Result<GameState, GameError> {
-        self.manager.update_game(game_id, user_id, direction).await
+        self.manager.update_game(principal, direction).await
+    }
+
+    /// Subscribe to the game. The receiver gets the GameState of every tick, ending with
+    /// the state that contains the game over reason. A receiver that falls too far behind
+    /// gets `RecvError::Lagged` and misses the oldest states it hadn't read.
+    pub async fn watch_game(
+        &self,
+        principal: Principal,
+    ) -> Result<broadcast::Receiver<GameState>, GameError> {
+        self.manager.watch_game(principal).await
+    }
+
+    /// List the games on this server, oldest first. Pass the `next_page_token` of a page
+    /// to get the following page.
+    pub async fn list_games(
+        &self,
+        filter: GameListFilter,
+        page_size: usize,
+        page_token: Option<String>,
+    ) -> Result<GameListPage, GameError> {
+        self.manager.list_games(filter, page_size, page_token).await
+    }
+
+    /// Start a session for a joined user that both streams game states and accepts votes
+    pub async fn play_game(&self, principal: Principal) -> Result<PlaySession, GameError> {
+        self.manager.play_game(principal).await
+    }
+}
+
+/// Settings chosen when a game is created
+#[derive(Debug, Clone)]
+pub struct GameSettings {
+    pub width: i32,
+    pub height: i32,
+    pub tick_duration_millis: u64,
+    pub empty_game_policy: EmptyGamePolicy,
+    pub board_mode: BoardMode,
+    /// Name of the level map the board is laid out from. The board takes the level's size.
+    pub level: Option<String>,
+    /// Players that send nothing for this long are counted as idle
+    pub afk_timeout: Duration,
+    /// Time given to players to react between starting the game and its first tick
+    pub start_countdown: Duration,
+    /// Time given to players to react before a paused game starts ticking again
+    pub resume_countdown: Duration,
+    /// Food items on the board at once
+    pub food_count: u32,
+    /// Chance, out of 100, that a new food item is special rather than regular
+    pub special_food_percent: u32,
+    /// Ticks a special food item stays on the board before it vanishes
+    pub special_food_lifetime: u32,
+    pub game_mode: GameMode,
+    /// Teams playing in team mode, numbered from 1
+    pub team_count: u32,
+    /// Seeds the game's random choices, such as where food appears. A random seed is
+    /// picked when unset.
+    pub seed: Option<u64>,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            width: 10,
+            height: 10,
+            tick_duration_millis: 500,
+            empty_game_policy: EmptyGamePolicy::End,
+            board_mode: BoardMode::Bounded,
+            level: None,
+            afk_timeout: Duration::from_secs(30),
+            start_countdown: Duration::from_secs(3),
+            resume_countdown: Duration::ZERO,
+            food_count: 1,
+            special_food_percent: 0,
+            special_food_lifetime: 20,
+            game_mode: GameMode::CrowdVote,
+            team_count: 2,
+            seed: None,
+        }
+    }
+}
+
+/// What happens when rusty reaches the edge of the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardMode {
+    /// Leaving the board ends the game
+    Bounded,
+    /// Rusty comes back in on the opposite edge
+    Wrap,
+}
+
+/// How the players steer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    /// Every player votes on the direction of a single rusty
+    CrowdVote,
+    /// Every player steers their own snake and the last snake standing wins
+    Arena,
+    /// Every team votes on the direction of its own snake and the last team standing wins
+    Teams,
+}
+
+/// What happens to a started game when its last player leaves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmptyGamePolicy {
+    End,
+    Pause,
+}
+
+/// Settings for the games hosted by a server
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Games that nobody has used for this long are removed
+    pub idle_game_ttl: Duration,
+    /// Games are removed this long after they are over
+    pub finished_game_ttl: Duration,
+    /// How often games are checked for removal
+    pub sweep_interval: Duration,
+    pub limits: GameLimits,
+    /// Directory of the level maps games can be laid out from
+    pub levels_dir: PathBuf,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            idle_game_ttl: Duration::from_secs(30 * 60),
+            finished_game_ttl: Duration::from_secs(5 * 60),
+            sweep_interval: Duration::from_secs(30),
+            limits: GameLimits::default(),
+            levels_dir: PathBuf::from("levels"),
+        }
     }
 }
 
+/// Bounds on the settings of the games a server hosts. Ranges are inclusive.
+#[derive(Debug, Clone)]
+pub struct GameLimits {
+    /// Rusty starts in the first three columns and the first food in the middle column, so
+    /// on narrower boards the food starts under rusty
+    pub min_width: i32,
+    pub max_width: i32,
+    pub min_height: i32,
+    pub max_height: i32,
+    pub min_tick_duration_millis: u64,
+    pub max_tick_duration_millis: u64,
+    /// Longest countdown before the first tick or before a paused game resumes
+    pub max_countdown: Duration,
+    pub max_food_count: u32,
+    pub max_team_count: u32,
+    /// Games that can exist on the server at once
+    pub max_games: usize,
+}
+
+impl Default for GameLimits {
+    fn default() -> Self {
+        Self {
+            min_width: 6,
+            max_width: 200,
+            min_height: 3,
+            max_height: 200,
+            min_tick_duration_millis: 50,
+            max_tick_duration_millis: 10_000,
+            max_countdown: Duration::from_secs(60),
+            max_food_count: 20,
+            max_team_count: 8,
+            max_games: 1000,
+        }
+    }
+}
+
+impl GameLimits {
+    /// Checks the settings against the limits, describing every setting that is out of range
+    pub(crate) fn validate(&self, settings: &GameSettings) -> Result<(), GameError> {
+        let mut problems = Vec::new();
+        if !(self.min_width..=self.max_width).contains(&settings.width) {
+            problems.push(format!(
+                "width must be between {} and {}, got {}",
+                self.min_width, self.max_width, settings.width
+            ));
+        }
+        if !(self.min_height..=self.max_height).contains(&settings.height) {
+            problems.push(format!(
+                "height must be between {} and {}, got {}",
+                self.min_height, self.max_height, settings.height
+            ));
+        }
+        let tick_range = self.min_tick_duration_millis..=self.max_tick_duration_millis;
+        if !tick_range.contains(&settings.tick_duration_millis) {
+            problems.push(format!(
+                "tick_duration_millis must be between {} and {}, got {}",
+                self.min_tick_duration_millis,
+                self.max_tick_duration_millis,
+                settings.tick_duration_millis
+            ));
+        }
+        let countdowns = [
+            ("start_countdown_millis", settings.start_countdown),
+            ("resume_countdown_millis", settings.resume_countdown),
+        ];
+        for (name, countdown) in countdowns {
+            if countdown > self.max_countdown {
+                problems.push(format!(
+                    "{} must be at most {}, got {}",
+                    name,
+                    self.max_countdown.as_millis(),
+                    countdown.as_millis()
+                ));
+            }
+        }
+        if !(1..=self.max_food_count).contains(&settings.food_count) {
+            problems.push(format!(
+                "food_count must be between 1 and {}, got {}",
+                self.max_food_count, settings.food_count
+            ));
+        }
+        if settings.special_food_percent > 100 {
+            problems.push(format!(
+                "special_food_percent must be at most 100, got {}",
+                settings.special_food_percent
+            ));
+        }
+        if settings.special_food_lifetime == 0 {
+            problems.push("special_food_lifetime_ticks must be at least 1".to_string());
+        }
+        if !(2..=self.max_team_count).contains(&settings.team_count) {
+            problems.push(format!(
+                "team_count must be between 2 and {}, got {}",
+                self.max_team_count, settings.team_count
+            ));
+        }
+        let snake_mode = match settings.game_mode {
+            GameMode::CrowdVote => None,
+            GameMode::Arena => Some("arena"),
+            GameMode::Teams => Some("team"),
+        };
+        if let (Some(snake_mode), Some(_)) = (snake_mode, &settings.level) {
+            problems.push(format!("level can't be used in {} mode", snake_mode));
+        }
+
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(GameError::InvalidArgument(format!(
+                "Invalid game settings: {}",
+                problems.join("; ")
+            ))),
+        }
+    }
+}
+
+/// Longest display name, in characters
+const MAX_DISPLAY_NAME_LENGTH: usize = 24;
+const MAX_AVATAR_LENGTH: usize = 256;
+
+/// How a player appears to the other players of a game
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlayerProfile {
+    pub display_name: String,
+    /// A `#rrggbb` color
+    pub color: Option<String>,
+    /// An avatar id or image URL, left for the UI to interpret
+    pub avatar: Option<String>,
+}
+
+impl PlayerProfile {
+    pub fn new(display_name: impl Into<String>) -> Self {
+        Self {
+            display_name: display_name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Trims the display name, then checks that every field is within its limits
+    pub(crate) fn validate(mut self) -> Result<Self, GameError> {
+        self.display_name = self.display_name.trim().to_string();
+        let name_length = self.display_name.chars().count();
+        if name_length == 0 || name_length > MAX_DISPLAY_NAME_LENGTH {
+            return Err(GameError::InvalidArgument(format!(
+                "Display name must be between 1 and {} characters, got {}",
+                MAX_DISPLAY_NAME_LENGTH, name_length
+            )));
+        }
+        if self.display_name.chars().any(char::is_control) {
+            return Err(GameError::InvalidArgument(
+                "Display name can't contain control characters".to_string(),
+            ));
+        }
+        if let Some(color) = &self.color {
+            let is_hex_color = color.len() == 7
+                && color.starts_with('#')
+                && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+            if !is_hex_color {
+                return Err(GameError::InvalidArgument(format!(
+                    "Color must look like #rrggbb, got {}",
+                    color
+                )));
+            }
+        }
+        if let Some(avatar) = &self.avatar {
+            if avatar.is_empty() || avatar.len() > MAX_AVATAR_LENGTH {
+                return Err(GameError::InvalidArgument(format!(
+                    "Avatar must be between 1 and {} bytes",
+                    MAX_AVATAR_LENGTH
+                )));
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// Players vote on rusty's direction. Spectators can only check the status and watch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JoinMode {
+    #[default]
+    Play,
+    Spectate,
+}
+
+/// A joined player or spectator, as listed in the game state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RosterEntry {
+    pub user_id: String,
+    pub profile: PlayerProfile,
+    pub is_host: bool,
+    /// Hasn't been heard from within the game's AFK timeout
+    pub is_idle: bool,
+    pub is_spectator: bool,
+    /// The player's team in team mode
+    pub team: Option<u32>,
+}
+
+#[derive(Debug)]
+pub struct CreateGameReply {
+    pub game_id: String,
+    /// The creator's user id, which holds the host role
+    pub host_user_id: String,
+    /// Authenticates the host's requests
+    pub session_token: String,
+}
+
 #[derive(Debug)]
 pub struct JoinGameReply {
     pub user_id: String,
     pub width: i32,
     pub height: i32,
+    /// Authenticates the user's requests
+    pub session_token: String,
+}
+
+/// Overview of a game, used to discover games without joining them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameSummary {
+    pub game_id: String,
+    pub width: i32,
+    pub height: i32,
+    pub tick_duration_millis: u64,
+    pub num_users: u32,
+    pub phase: GamePhase,
+    pub created_at: SystemTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameListFilter {
+    All,
+    /// Games that are waiting in the lobby for players
+    Joinable,
+    /// Games that have started and are not over
+    Running,
+}
+
+impl GameListFilter {
+    pub(crate) fn matches(&self, phase: GamePhase) -> bool {
+        match self {
+            GameListFilter::All => true,
+            GameListFilter::Joinable => phase == GamePhase::Lobby,
+            GameListFilter::Running => matches!(
+                phase,
+                GamePhase::Countdown | GamePhase::Running | GamePhase::Paused
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GameListPage {
+    pub games: Vec<GameSummary>,
+    /// Set when there are more games after this page
+    pub next_page_token: Option<String>,
 }
 
 #[derive(Debug)]
 pub enum GameError {
-    InvalidUser,
-    InvalidGame,
-    Internal,
+    /// No game has the given id
+    GameNotFound(String),
+    /// The user id hasn't joined the game
+    UserNotFound(String),
+    AlreadyStarted,
+    NotStarted,
+    GameOver,
+    InvalidTransition {
+        from: GamePhase,
+        to: GamePhase,
+    },
+    InvalidArgument(String),
+    /// The request has no valid session token
+    Unauthenticated,
+    /// Only the host can do this
+    PermissionDenied,
+    /// Another player of the game already uses the display name
+    NameTaken(String),
+    /// Spectators can't vote
+    Spectating,
+    /// The server is hosting as many games as it allows
+    Full {
+        max_games: usize,
+    },
+    /// The user sent too many requests and can try again after the given time
+    RateLimited {
+        retry_after: Duration,
+    },
+    /// Something went wrong on the server, described by the context
+    Internal(String),
 }
 
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::GameNotFound(game_id) => {
+                write!(f, "Game {} doesn't exist. Create a game first.", game_id)
+            }
+            GameError::UserNotFound(user_id) => {
+                write!(f, "User {} hasn't joined the game. Join first.", user_id)
+            }
+            GameError::AlreadyStarted => write!(f, "The game has already started."),
+            GameError::NotStarted => write!(f, "The game has not started yet."),
+            GameError::GameOver => write!(f, "The game is over."),
+            GameError::InvalidTransition { from, to } => {
+                write!(f, "A {:?} game can't move to {:?}.", from, to)
+            }
+            GameError::InvalidArgument(message) => write!(f, "{}", message),
+            GameError::Unauthenticated => {
+                write!(f, "A valid session token is required. Join the game first.")
+            }
+            GameError::PermissionDenied => write!(f, "Only the host can do that."),
+            GameError::NameTaken(display_name) => {
+                write!(
+                    f,
+                    "The name {} is already taken in this game.",
+                    display_name
+                )
+            }
+            GameError::Spectating => write!(f, "Spectators can't vote. Join as a player."),
+            GameError::Full { max_games } => {
+                write!(f, "The server is already hosting {} games.", max_games)
+            }
+            GameError::RateLimited { retry_after } => write!(
+                f,
+                "Too many requests. Try again in {}ms.",
+                retry_after.as_millis()
+            ),
+            GameError::Internal(context) => write!(f, "Internal error: {}", context),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
 impl From<Direction> for proto::MoveDirection {
     fn from(s: Direction) -> Self {
         match s {
@@ -90,24 +632,553 @@ impl From<Direction> for proto::MoveDirection {
     }
 }
 
-impl From<i32> for Direction {
-    fn from(s: i32) -> Self {
-        match proto::MoveDirection::from_i32(s).unwrap() {
-            proto::MoveDirection::East => Direction::East,
-            proto::MoveDirection::North => Direction::North,
-            proto::MoveDirection::South => Direction::South,
-            proto::MoveDirection::West => Direction::West,
+/// Reads an enum field of a proto message, rejecting values this build doesn't know
+pub(crate) fn read_proto_enum<T>(
+    value: i32,
+    from_i32: fn(i32) -> Option<T>,
+    field: &str,
+) -> Result<T, GameError> {
+    from_i32(value)
+        .ok_or_else(|| GameError::InvalidArgument(format!("unknown {} {}", field, value)))
+}
+
+impl TryFrom<i32> for Direction {
+    type Error = GameError;
+
+    fn try_from(s: i32) -> Result<Self, Self::Error> {
+        match proto::MoveDirection::from_i32(s) {
+            Some(proto::MoveDirection::East) => Ok(Direction::East),
+            Some(proto::MoveDirection::North) => Ok(Direction::North),
+            Some(proto::MoveDirection::South) => Ok(Direction::South),
+            Some(proto::MoveDirection::West) => Ok(Direction::West),
+            None => Err(GameError::InvalidArgument(format!(
+                "unknown move direction {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl From<GameOverReason> for proto::GameOverReason {
+    fn from(s: GameOverReason) -> Self {
+        match s {
+            GameOverReason::OutOfBounds => proto::GameOverReason::OutOfBounds,
+            GameOverReason::HitWall => proto::GameOverReason::HitWall,
+            GameOverReason::CollideWithSelf => proto::GameOverReason::CollideWithSelf,
+            GameOverReason::Winner => proto::GameOverReason::Winner,
+            GameOverReason::Abandoned => proto::GameOverReason::Abandoned,
+            GameOverReason::HeadToHead => proto::GameOverReason::HeadToHead,
+            GameOverReason::HitSnake => proto::GameOverReason::HitSnake,
+            GameOverReason::Draw => proto::GameOverReason::Draw,
+        }
+    }
+}
+
+impl From<proto::GameOverReason> for Option<GameOverReason> {
+    fn from(s: proto::GameOverReason) -> Self {
+        match s {
+            proto::GameOverReason::NotOver => None,
+            proto::GameOverReason::OutOfBounds => Some(GameOverReason::OutOfBounds),
+            proto::GameOverReason::HitWall => Some(GameOverReason::HitWall),
+            proto::GameOverReason::CollideWithSelf => Some(GameOverReason::CollideWithSelf),
+            proto::GameOverReason::Winner => Some(GameOverReason::Winner),
+            proto::GameOverReason::Abandoned => Some(GameOverReason::Abandoned),
+            proto::GameOverReason::HeadToHead => Some(GameOverReason::HeadToHead),
+            proto::GameOverReason::HitSnake => Some(GameOverReason::HitSnake),
+            proto::GameOverReason::Draw => Some(GameOverReason::Draw),
+        }
+    }
+}
+
+impl From<GamePhase> for proto::GamePhase {
+    fn from(s: GamePhase) -> Self {
+        match s {
+            GamePhase::Lobby => proto::GamePhase::Lobby,
+            GamePhase::Countdown => proto::GamePhase::Countdown,
+            GamePhase::Running => proto::GamePhase::Running,
+            GamePhase::Paused => proto::GamePhase::Paused,
+            GamePhase::Finished => proto::GamePhase::Finished,
+        }
+    }
+}
+
+impl From<proto::GamePhase> for GamePhase {
+    fn from(s: proto::GamePhase) -> Self {
+        match s {
+            proto::GamePhase::Lobby => GamePhase::Lobby,
+            proto::GamePhase::Countdown => GamePhase::Countdown,
+            proto::GamePhase::Running => GamePhase::Running,
+            proto::GamePhase::Paused => GamePhase::Paused,
+            proto::GamePhase::Finished => GamePhase::Finished,
         }
     }
 }
 
 impl From<GameState> for proto::GameState {
     fn from(game_state: GameState) -> Self {
+        let game_over_reason = match game_state.game_over_reason {
+            Some(reason) => proto::GameOverReason::from(reason),
+            None => proto::GameOverReason::NotOver,
+        };
+        let (winner_id, winning_team) = game_state
+            .winner
+            .map_or((String::new(), 0), SnakeOwner::into_proto_fields);
         Self {
             number_of_players: game_state.num_users,
-            food: Some(game_state.food.into()),
+            number_of_active_players: game_state.num_active,
+            number_of_idle_players: game_state.num_idle,
+            number_of_spectators: game_state.num_spectators,
+            score: game_state.score,
+            series: Some(game_state.series.into()),
+            roster: game_state.roster.into_iter().map(|p| p.into()).collect(),
+            food_items: game_state.food.into_iter().map(|f| f.into()).collect(),
+            speed_effect: game_state.speed_effect.map(|effect| effect.into()),
+            game_mode: proto::GameMode::from(game_state.game_mode).into(),
+            snakes: game_state.snakes.into_iter().map(|s| s.into()).collect(),
+            winner_id,
+            winning_team,
+            seed: game_state.seed,
             body: game_state.body.into_iter().map(|p| p.into()).collect(),
             move_direction: proto::MoveDirection::into(game_state.direction.into()),
+            game_over_reason: game_over_reason.into(),
+            phase: proto::GamePhase::from(game_state.phase).into(),
+            board_mode: proto::BoardMode::from(game_state.board_mode).into(),
+            walls: game_state.walls.into_iter().map(|p| p.into()).collect(),
+            countdown_remaining_millis: game_state
+                .countdown_remaining
+                .map_or(0, |remaining| remaining.as_millis() as u64),
+            // Whole seconds, rounded up so the last second of the countdown shows as 1
+            countdown_remaining_seconds: game_state
+                .countdown_remaining
+                .map_or(0, |remaining| remaining.as_millis().div_ceil(1000) as u32),
+            first_tick_at_millis: game_state.first_tick_at.map_or(0, |first_tick_at| {
+                first_tick_at
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64
+            }),
+            tick: game_state.tick as u64,
+            height: game_state.height as u32,
+            width: game_state.width as u32,
+        }
+    }
+}
+
+/// Fails on a game state the client can't make sense of, like one with a phase or game over
+/// reason added by a newer server
+impl TryFrom<proto::GameState> for GameState {
+    type Error = GameError;
+
+    fn try_from(game_state: proto::GameState) -> Result<Self, Self::Error> {
+        let phase =
+            read_proto_enum(game_state.phase, proto::GamePhase::from_i32, "game phase")?.into();
+        let game_over_reason = read_proto_enum(
+            game_state.game_over_reason,
+            proto::GameOverReason::from_i32,
+            "game over reason",
+        )?;
+        let board_mode = read_proto_enum(
+            game_state.board_mode,
+            proto::BoardMode::from_i32,
+            "board mode",
+        )?;
+        let game_mode =
+            read_proto_enum(game_state.game_mode, proto::GameMode::from_i32, "game mode")?;
+        let series = game_state
+            .series
+            .ok_or_else(|| GameError::InvalidArgument("missing series score".to_string()))?;
+        let food = game_state
+            .food_items
+            .into_iter()
+            .map(FoodItem::try_from)
+            .collect::<Result<_, _>>()?;
+        // The countdown is only meaningful while counting down, zero is sent otherwise
+        let (countdown_remaining, first_tick_at) = match phase {
+            GamePhase::Countdown => (
+                Some(Duration::from_millis(game_state.countdown_remaining_millis)),
+                Some(UNIX_EPOCH + Duration::from_millis(game_state.first_tick_at_millis)),
+            ),
+            _ => (None, None),
+        };
+
+        Ok(Self {
+            height: game_state.height as i32,
+            width: game_state.width as i32,
+            tick: game_state.tick as usize,
+            game_over_reason: game_over_reason.into(),
+            phase,
+            board_mode: board_mode.into(),
+            walls: game_state.walls.into_iter().map(|p| p.into()).collect(),
+            countdown_remaining,
+            first_tick_at,
+            direction: Direction::try_from(game_state.move_direction)?,
+            num_users: game_state.number_of_players,
+            num_active: game_state.number_of_active_players,
+            num_idle: game_state.number_of_idle_players,
+            num_spectators: game_state.number_of_spectators,
+            score: game_state.score,
+            series: series.into(),
+            roster: game_state.roster.into_iter().map(|p| p.into()).collect(),
+            body: game_state.body.into_iter().map(|p| p.into()).collect(),
+            food,
+            speed_effect: game_state
+                .speed_effect
+                .map(SpeedEffect::try_from)
+                .transpose()?,
+            game_mode: game_mode.into(),
+            snakes: game_state
+                .snakes
+                .into_iter()
+                .map(SnakeState::try_from)
+                .collect::<Result<_, _>>()?,
+            winner: match (game_state.winner_id.is_empty(), game_state.winning_team) {
+                (true, 0) => None,
+                _ => Some(SnakeOwner::from_proto_fields(
+                    game_state.winner_id,
+                    game_state.winning_team,
+                )),
+            },
+            seed: game_state.seed,
+        })
+    }
+}
+
+impl From<SeriesScore> for proto::SeriesScore {
+    fn from(series: SeriesScore) -> Self {
+        Self {
+            round: series.round,
+            total_score: series.total_score,
+            best_score: series.best_score,
+        }
+    }
+}
+
+impl From<proto::SeriesScore> for SeriesScore {
+    fn from(series: proto::SeriesScore) -> Self {
+        Self {
+            round: series.round,
+            total_score: series.total_score,
+            best_score: series.best_score,
+        }
+    }
+}
+
+impl From<PlayerProfile> for proto::PlayerProfile {
+    fn from(profile: PlayerProfile) -> Self {
+        Self {
+            display_name: profile.display_name,
+            color: profile.color.unwrap_or_default(),
+            avatar: profile.avatar.unwrap_or_default(),
+        }
+    }
+}
+
+/// Unset colors and avatars are sent as empty strings
+impl From<proto::PlayerProfile> for PlayerProfile {
+    fn from(profile: proto::PlayerProfile) -> Self {
+        let non_empty = |value: String| (!value.is_empty()).then_some(value);
+        Self {
+            display_name: profile.display_name,
+            color: non_empty(profile.color),
+            avatar: non_empty(profile.avatar),
+        }
+    }
+}
+
+impl From<RosterEntry> for proto::Player {
+    fn from(player: RosterEntry) -> Self {
+        Self {
+            user_id: player.user_id,
+            profile: Some(player.profile.into()),
+            is_host: player.is_host,
+            is_idle: player.is_idle,
+            is_spectator: player.is_spectator,
+            team: player.team.unwrap_or(0),
+        }
+    }
+}
+
+impl From<proto::Player> for RosterEntry {
+    fn from(player: proto::Player) -> Self {
+        Self {
+            user_id: player.user_id,
+            profile: player.profile.unwrap_or_default().into(),
+            is_host: player.is_host,
+            is_idle: player.is_idle,
+            is_spectator: player.is_spectator,
+            team: Some(player.team).filter(|team| *team > 0),
+        }
+    }
+}
+
+impl From<proto::JoinMode> for JoinMode {
+    fn from(mode: proto::JoinMode) -> Self {
+        match mode {
+            proto::JoinMode::JoinAsPlayer => JoinMode::Play,
+            proto::JoinMode::JoinAsSpectator => JoinMode::Spectate,
+        }
+    }
+}
+
+impl From<BoardMode> for proto::BoardMode {
+    fn from(mode: BoardMode) -> Self {
+        match mode {
+            BoardMode::Bounded => proto::BoardMode::Bounded,
+            BoardMode::Wrap => proto::BoardMode::WrapAround,
+        }
+    }
+}
+
+impl From<proto::BoardMode> for BoardMode {
+    fn from(mode: proto::BoardMode) -> Self {
+        match mode {
+            proto::BoardMode::Bounded => BoardMode::Bounded,
+            proto::BoardMode::WrapAround => BoardMode::Wrap,
+        }
+    }
+}
+
+impl From<GameMode> for proto::GameMode {
+    fn from(mode: GameMode) -> Self {
+        match mode {
+            GameMode::CrowdVote => proto::GameMode::CrowdVote,
+            GameMode::Arena => proto::GameMode::Arena,
+            GameMode::Teams => proto::GameMode::Teams,
+        }
+    }
+}
+
+impl From<proto::GameMode> for GameMode {
+    fn from(mode: proto::GameMode) -> Self {
+        match mode {
+            proto::GameMode::CrowdVote => GameMode::CrowdVote,
+            proto::GameMode::Arena => GameMode::Arena,
+            proto::GameMode::Teams => GameMode::Teams,
+        }
+    }
+}
+
+impl From<SnakeState> for proto::Snake {
+    fn from(snake: SnakeState) -> Self {
+        let elimination_reason = match snake.eliminated {
+            Some(reason) => proto::GameOverReason::from(reason),
+            None => proto::GameOverReason::NotOver,
+        };
+        let (user_id, team) = snake.owner.into_proto_fields();
+        Self {
+            user_id,
+            team,
+            body: snake.body.into_iter().map(|p| p.into()).collect(),
+            move_direction: proto::MoveDirection::into(snake.direction.into()),
+            score: snake.score,
+            elimination_reason: elimination_reason.into(),
+        }
+    }
+}
+
+impl TryFrom<proto::Snake> for SnakeState {
+    type Error = GameError;
+
+    fn try_from(snake: proto::Snake) -> Result<Self, Self::Error> {
+        let elimination_reason = read_proto_enum(
+            snake.elimination_reason,
+            proto::GameOverReason::from_i32,
+            "elimination reason",
+        )?;
+        Ok(Self {
+            owner: SnakeOwner::from_proto_fields(snake.user_id, snake.team),
+            body: snake.body.into_iter().map(|p| p.into()).collect(),
+            direction: Direction::try_from(snake.move_direction)?,
+            score: snake.score,
+            eliminated: elimination_reason.into(),
+        })
+    }
+}
+
+impl From<FoodKind> for proto::FoodKind {
+    fn from(kind: FoodKind) -> Self {
+        match kind {
+            FoodKind::Regular => proto::FoodKind::RegularFood,
+            FoodKind::Bonus => proto::FoodKind::BonusFood,
+            FoodKind::Shrink => proto::FoodKind::ShrinkFood,
+            FoodKind::SpeedUp => proto::FoodKind::SpeedUpFood,
+            FoodKind::SlowDown => proto::FoodKind::SlowDownFood,
+        }
+    }
+}
+
+impl From<proto::FoodKind> for FoodKind {
+    fn from(kind: proto::FoodKind) -> Self {
+        match kind {
+            proto::FoodKind::RegularFood => FoodKind::Regular,
+            proto::FoodKind::BonusFood => FoodKind::Bonus,
+            proto::FoodKind::ShrinkFood => FoodKind::Shrink,
+            proto::FoodKind::SpeedUpFood => FoodKind::SpeedUp,
+            proto::FoodKind::SlowDownFood => FoodKind::SlowDown,
+        }
+    }
+}
+
+impl From<FoodItem> for proto::Food {
+    fn from(food: FoodItem) -> Self {
+        Self {
+            position: Some(food.position.into()),
+            kind: proto::FoodKind::from(food.kind).into(),
+            ticks_left: food.ticks_left.unwrap_or(0),
+        }
+    }
+}
+
+impl TryFrom<proto::Food> for FoodItem {
+    type Error = GameError;
+
+    fn try_from(food: proto::Food) -> Result<Self, Self::Error> {
+        let kind = read_proto_enum(food.kind, proto::FoodKind::from_i32, "food kind")?;
+        let position = food
+            .position
+            .ok_or_else(|| GameError::InvalidArgument("food without a position".to_string()))?;
+        Ok(Self {
+            position: position.into(),
+            kind: kind.into(),
+            // Only special food expires, zero is sent for regular food
+            ticks_left: Some(food.ticks_left).filter(|ticks_left| *ticks_left > 0),
+        })
+    }
+}
+
+impl From<SpeedEffect> for proto::SpeedEffect {
+    fn from(effect: SpeedEffect) -> Self {
+        Self {
+            kind: proto::FoodKind::from(effect.kind).into(),
+            ticks_left: effect.ticks_left,
+        }
+    }
+}
+
+impl TryFrom<proto::SpeedEffect> for SpeedEffect {
+    type Error = GameError;
+
+    fn try_from(effect: proto::SpeedEffect) -> Result<Self, Self::Error> {
+        let kind = read_proto_enum(effect.kind, proto::FoodKind::from_i32, "speed effect kind")?;
+        Ok(Self {
+            kind: kind.into(),
+            ticks_left: effect.ticks_left,
+        })
+    }
+}
+
+impl From<proto::EmptyGamePolicy> for EmptyGamePolicy {
+    fn from(s: proto::EmptyGamePolicy) -> Self {
+        match s {
+            proto::EmptyGamePolicy::EndGame => EmptyGamePolicy::End,
+            proto::EmptyGamePolicy::PauseGame => EmptyGamePolicy::Pause,
+        }
+    }
+}
+
+/// Settings sent when creating a game. An unset AFK timeout, start countdown, food count,
+/// special food lifetime or team count uses the default, and an unset seed picks a random one.
+impl TryFrom<&proto::CreateRequest> for GameSettings {
+    type Error = GameError;
+
+    fn try_from(request: &proto::CreateRequest) -> Result<Self, Self::Error> {
+        let defaults = GameSettings::default();
+        let empty_game_policy = read_proto_enum(
+            request.empty_game_policy,
+            proto::EmptyGamePolicy::from_i32,
+            "empty game policy",
+        )?;
+        let board_mode =
+            read_proto_enum(request.board_mode, proto::BoardMode::from_i32, "board mode")?;
+        let game_mode = read_proto_enum(request.game_mode, proto::GameMode::from_i32, "game mode")?;
+        let afk_timeout = match request.afk_timeout_millis {
+            0 => defaults.afk_timeout,
+            millis => Duration::from_millis(millis as u64),
+        };
+        let start_countdown = match request.start_countdown_millis {
+            0 => defaults.start_countdown,
+            millis => Duration::from_millis(millis as u64),
+        };
+        Ok(Self {
+            width: request.width as i32,
+            height: request.height as i32,
+            tick_duration_millis: request.tick_duration_millis as u64,
+            empty_game_policy: empty_game_policy.into(),
+            board_mode: board_mode.into(),
+            game_mode: game_mode.into(),
+            team_count: match request.team_count {
+                0 => defaults.team_count,
+                count => count,
+            },
+            level: Some(request.level.clone()).filter(|level| !level.is_empty()),
+            food_count: match request.food_count {
+                0 => defaults.food_count,
+                count => count,
+            },
+            special_food_percent: request.special_food_percent,
+            special_food_lifetime: match request.special_food_lifetime_ticks {
+                0 => defaults.special_food_lifetime,
+                ticks => ticks,
+            },
+            afk_timeout,
+            start_countdown,
+            resume_countdown: Duration::from_millis(request.resume_countdown_millis as u64),
+            seed: request.seed,
+        })
+    }
+}
+
+/// Settings sent when configuring a game, which carries the same settings as a create
+/// request and falls back to the same defaults
+impl TryFrom<&proto::ConfigureRequest> for GameSettings {
+    type Error = GameError;
+
+    fn try_from(request: &proto::ConfigureRequest) -> Result<Self, Self::Error> {
+        GameSettings::try_from(&proto::CreateRequest {
+            height: request.height,
+            width: request.width,
+            tick_duration_millis: request.tick_duration_millis,
+            empty_game_policy: request.empty_game_policy,
+            afk_timeout_millis: request.afk_timeout_millis,
+            resume_countdown_millis: request.resume_countdown_millis,
+            host_profile: None,
+            start_countdown_millis: request.start_countdown_millis,
+            board_mode: request.board_mode,
+            level: request.level.clone(),
+            food_count: request.food_count,
+            special_food_percent: request.special_food_percent,
+            special_food_lifetime_ticks: request.special_food_lifetime_ticks,
+            game_mode: request.game_mode,
+            team_count: request.team_count,
+            seed: request.seed,
+        })
+    }
+}
+
+impl From<proto::GameFilter> for GameListFilter {
+    fn from(s: proto::GameFilter) -> Self {
+        match s {
+            proto::GameFilter::AllGames => GameListFilter::All,
+            proto::GameFilter::JoinableGames => GameListFilter::Joinable,
+            proto::GameFilter::RunningGames => GameListFilter::Running,
+        }
+    }
+}
+
+impl From<GameSummary> for proto::GameSummary {
+    fn from(summary: GameSummary) -> Self {
+        let created_at = summary
+            .created_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            game_id: summary.game_id,
+            height: summary.height as u32,
+            width: summary.width as u32,
+            tick_duration_millis: summary.tick_duration_millis as u32,
+            number_of_players: summary.num_users,
+            phase: proto::GamePhase::from(summary.phase).into(),
+            created_at_millis: created_at.as_millis() as u64,
         }
     }
 }
@@ -121,7 +1192,16 @@ impl From<Point> for proto::Point {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+impl From<proto::Point> for Point {
+    fn from(point: proto::Point) -> Self {
+        Self {
+            x: point.x,
+            y: point.y,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct Point {
     pub x: i32,
     pub y: i32,
@@ -140,6 +1220,11 @@ impl Point {
             Direction::West => Point::new(self.x - 1, self.y),
         }
     }
+
+    /// Brings a point that left a board of the given size back in on the opposite edge
+    pub fn wrap(&self, width: i32, height: i32) -> Point {
+        Point::new(self.x.rem_euclid(width), self.y.rem_euclid(height))
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -148,10 +1233,140 @@ pub struct GameState {
     pub width: i32,
     pub tick: usize,
     pub game_over_reason: Option<GameOverReason>,
+    pub phase: GamePhase,
+    pub board_mode: BoardMode,
+    /// Cells that end the game when rusty runs into them, row by row
+    pub walls: Vec<Point>,
+    /// Time left before the first tick while the game is counting down
+    pub countdown_remaining: Option<Duration>,
+    /// When the first tick is due while the game is counting down
+    pub first_tick_at: Option<SystemTime>,
     pub direction: Direction,
+    /// Players that joined, whether active or idle
     pub num_users: u32,
+    pub num_active: u32,
+    /// Players that haven't been heard from within the game's AFK timeout
+    pub num_idle: u32,
+    /// Users watching the game, who aren't counted as players
+    pub num_spectators: u32,
+    /// Points scored from food by rusty this round
+    pub score: u32,
+    pub series: SeriesScore,
+    /// Every joined player and spectator, ordered by display name
+    pub roster: Vec<RosterEntry>,
     pub body: Vec<Point>,
-    pub food: Point,
+    pub food: Vec<FoodItem>,
+    /// A speed change from special food that is still wearing off
+    pub speed_effect: Option<SpeedEffect>,
+    pub game_mode: GameMode,
+    /// The players' snakes in arena mode or the teams' snakes in team mode, ordered by
+    /// owner. Empty in crowd vote mode, where `body` is rusty.
+    pub snakes: Vec<SnakeState>,
+    /// The last snake standing once an arena or team game is won
+    pub winner: Option<SnakeOwner>,
+    /// The seed of this round's random choices. Creating a game with it and sending the
+    /// same votes on every tick replays the round.
+    pub seed: u64,
+}
+
+/// Who steers a snake of an arena or team game
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SnakeOwner {
+    /// The user id of the player steering the snake in arena mode
+    Player(String),
+    /// The team voting on the snake's direction in team mode
+    Team(u32),
+}
+
+impl SnakeOwner {
+    /// The user id and team sent for the owner, leaving the one that doesn't apply empty
+    fn into_proto_fields(self) -> (String, u32) {
+        match self {
+            SnakeOwner::Player(user_id) => (user_id, 0),
+            SnakeOwner::Team(team) => (String::new(), team),
+        }
+    }
+
+    fn from_proto_fields(user_id: String, team: u32) -> Self {
+        match team {
+            0 => SnakeOwner::Player(user_id),
+            team => SnakeOwner::Team(team),
+        }
+    }
+}
+
+impl fmt::Display for SnakeOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnakeOwner::Player(user_id) => write!(f, "{}", user_id),
+            SnakeOwner::Team(team) => write!(f, "Team {}", team),
+        }
+    }
+}
+
+/// A player's own snake in arena mode, or a team's snake in team mode
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SnakeState {
+    pub owner: SnakeOwner,
+    pub body: Vec<Point>,
+    pub direction: Direction,
+    /// Points scored from food this round
+    pub score: u32,
+    /// Why the snake is out, or None while it is still in play
+    pub eliminated: Option<GameOverReason>,
+}
+
+/// What eating a food item does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoodKind {
+    /// Grows rusty and scores a point
+    Regular,
+    /// Grows rusty and scores `BONUS_POINTS`
+    Bonus,
+    /// Takes segments off rusty's tail, down to its starting length
+    Shrink,
+    /// Grows rusty and halves the tick duration for a while
+    SpeedUp,
+    /// Grows rusty and doubles the tick duration for a while
+    SlowDown,
+}
+
+/// Points scored by eating bonus food
+pub const BONUS_POINTS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoodItem {
+    pub position: Point,
+    pub kind: FoodKind,
+    /// Ticks until the item vanishes. Regular food stays until it is eaten.
+    pub ticks_left: Option<u32>,
+}
+
+impl FoodItem {
+    pub fn regular(position: Point) -> Self {
+        Self {
+            position,
+            kind: FoodKind::Regular,
+            ticks_left: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpeedEffect {
+    /// Either `SpeedUp` or `SlowDown`
+    pub kind: FoodKind,
+    pub ticks_left: u32,
+}
+
+/// Scores across the rounds played by the same players in a game
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SeriesScore {
+    /// The round being played, starting at 1
+    pub round: u32,
+    /// Sum of the scores of the finished rounds
+    pub total_score: u32,
+    pub best_score: u32,
 }
 
 /// Provided by the requester and used by the manager task to send
@@ -161,14 +1376,43 @@ type Responder<T> = oneshot::Sender<T>;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum GameOverReason {
     OutOfBounds,
+    HitWall,
     CollideWithSelf,
-    // Rusty has filled every available space
+    // Rusty has filled every available space, or a snake is the last one standing in an arena.
+    // Also ends a game whose board is too full to place any more food.
     Winner,
+    // Every player left the game, or in an arena, the snake's player left
+    Abandoned,
+    // Two snakes of an arena met head to head
+    HeadToHead,
+    // A snake of an arena ran into another snake
+    HitSnake,
+    // The last snakes of an arena were all eliminated on the same tick, or the longest snakes
+    // were the same length when the board filled up
+    Draw,
+}
+
+/// Lifecycle of a game. A game waits in the lobby for players, counts down before
+/// the first tick, then runs until the game is over. Running games can be paused and
+/// count down again before resuming.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamePhase {
+    Lobby,
+    Countdown,
+    Running,
+    Paused,
+    Finished,
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{types::Direction, Point};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::{
+        proto, types::Direction, BoardMode, FoodItem, FoodKind, GameError, GameMode,
+        GameOverReason, GamePhase, GameSettings, GameState, PlayerProfile, Point, RosterEntry,
+        SeriesScore, SnakeOwner, SnakeState, SpeedEffect,
+    };
 
     #[test]
     fn add_direction() {
@@ -184,4 +1428,262 @@ mod tests {
             assert_eq!(Point::new(0, 0).add_direction(&direction), expected_point);
         }
     }
+
+    #[test]
+    fn wrap() {
+        assert_eq!(Point::new(-1, 4).wrap(10, 5), Point::new(9, 4));
+        assert_eq!(Point::new(10, 5).wrap(10, 5), Point::new(0, 0));
+        assert_eq!(Point::new(3, -1).wrap(10, 5), Point::new(3, 4));
+        assert_eq!(Point::new(3, 2).wrap(10, 5), Point::new(3, 2));
+    }
+
+    #[test]
+    fn settings_from_requests() {
+        let defaults = GameSettings::default();
+        let settings = GameSettings::try_from(&proto::CreateRequest {
+            height: 10,
+            width: 20,
+            board_mode: proto::BoardMode::WrapAround as i32,
+            special_food_percent: 25,
+            resume_countdown_millis: 500,
+            ..proto::CreateRequest::default()
+        })
+        .unwrap();
+        assert_eq!((settings.width, settings.height), (20, 10));
+        assert_eq!(settings.board_mode, BoardMode::Wrap);
+        assert_eq!(settings.special_food_percent, 25);
+        assert_eq!(settings.resume_countdown, Duration::from_millis(500));
+        assert_eq!(settings.afk_timeout, defaults.afk_timeout);
+        assert_eq!(settings.food_count, defaults.food_count);
+        assert_eq!(settings.level, None);
+        assert_eq!(settings.seed, None);
+
+        let settings = GameSettings::try_from(&proto::ConfigureRequest {
+            game_id: "game".to_string(),
+            food_count: 3,
+            game_mode: proto::GameMode::Arena as i32,
+            level: "maze".to_string(),
+            seed: Some(7),
+            ..proto::ConfigureRequest::default()
+        })
+        .unwrap();
+        assert_eq!(settings.food_count, 3);
+        assert_eq!(settings.game_mode, GameMode::Arena);
+        assert_eq!(settings.level.as_deref(), Some("maze"));
+        assert_eq!(settings.seed, Some(7));
+
+        // 0 is a seed like any other rather than the unset value
+        let settings = GameSettings::try_from(&proto::CreateRequest {
+            seed: Some(0),
+            ..proto::CreateRequest::default()
+        })
+        .unwrap();
+        assert_eq!(settings.seed, Some(0));
+        assert_eq!(settings.start_countdown, defaults.start_countdown);
+
+        // Modes this build doesn't know are rejected rather than read as the defaults
+        let unknown = [
+            proto::CreateRequest {
+                board_mode: 99,
+                ..proto::CreateRequest::default()
+            },
+            proto::CreateRequest {
+                game_mode: 99,
+                ..proto::CreateRequest::default()
+            },
+            proto::CreateRequest {
+                empty_game_policy: 99,
+                ..proto::CreateRequest::default()
+            },
+        ];
+        for request in unknown {
+            assert!(matches!(
+                GameSettings::try_from(&request),
+                Err(GameError::InvalidArgument(_))
+            ));
+        }
+        assert!(matches!(
+            GameSettings::try_from(&proto::ConfigureRequest {
+                board_mode: 99,
+                ..proto::ConfigureRequest::default()
+            }),
+            Err(GameError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn game_state_proto_round_trip() {
+        let game_states = [
+            GameState {
+                height: 12,
+                width: 30,
+                tick: 42,
+                game_over_reason: Some(GameOverReason::CollideWithSelf),
+                phase: GamePhase::Finished,
+                board_mode: BoardMode::Bounded,
+                walls: Vec::new(),
+                countdown_remaining: None,
+                first_tick_at: None,
+                direction: Direction::West,
+                num_users: 3,
+                num_active: 3,
+                num_idle: 0,
+                num_spectators: 0,
+                score: 0,
+                series: SeriesScore {
+                    round: 1,
+                    total_score: 0,
+                    best_score: 0,
+                },
+                roster: vec![
+                    RosterEntry {
+                        user_id: "host".to_string(),
+                        profile: PlayerProfile {
+                            display_name: "Host".to_string(),
+                            color: Some("#00ff00".to_string()),
+                            avatar: Some("snake".to_string()),
+                        },
+                        is_host: true,
+                        is_idle: false,
+                        is_spectator: false,
+                        team: None,
+                    },
+                    RosterEntry {
+                        user_id: "player".to_string(),
+                        profile: PlayerProfile::new("Player"),
+                        is_host: false,
+                        is_idle: true,
+                        is_spectator: false,
+                        team: Some(2),
+                    },
+                ],
+                body: vec![Point::new(4, 7), Point::new(5, 7), Point::new(6, 7)],
+                food: vec![
+                    FoodItem::regular(Point::new(29, 11)),
+                    FoodItem {
+                        position: Point::new(3, 3),
+                        kind: FoodKind::SlowDown,
+                        ticks_left: Some(12),
+                    },
+                ],
+                speed_effect: Some(SpeedEffect {
+                    kind: FoodKind::SpeedUp,
+                    ticks_left: 4,
+                }),
+                game_mode: GameMode::Arena,
+                snakes: vec![
+                    SnakeState {
+                        owner: SnakeOwner::Player("host".to_string()),
+                        body: vec![Point::new(9, 2), Point::new(8, 2), Point::new(7, 2)],
+                        direction: Direction::East,
+                        score: 2,
+                        eliminated: None,
+                    },
+                    SnakeState {
+                        owner: SnakeOwner::Player("player".to_string()),
+                        body: vec![Point::new(9, 3), Point::new(10, 3), Point::new(11, 3)],
+                        direction: Direction::West,
+                        score: 0,
+                        eliminated: Some(GameOverReason::HeadToHead),
+                    },
+                ],
+                winner: Some(SnakeOwner::Player("host".to_string())),
+                seed: 0,
+            },
+            GameState {
+                height: 5,
+                width: 5,
+                tick: 0,
+                game_over_reason: None,
+                phase: GamePhase::Countdown,
+                board_mode: BoardMode::Bounded,
+                walls: Vec::new(),
+                countdown_remaining: Some(Duration::from_millis(1500)),
+                first_tick_at: Some(UNIX_EPOCH + Duration::from_millis(1_700_000_001_500)),
+                direction: Direction::East,
+                num_users: 1,
+                num_active: 1,
+                num_idle: 0,
+                num_spectators: 0,
+                score: 0,
+                series: SeriesScore {
+                    round: 1,
+                    total_score: 0,
+                    best_score: 0,
+                },
+                roster: Vec::new(),
+                body: vec![Point::new(2, 2), Point::new(1, 2), Point::new(0, 2)],
+                food: vec![FoodItem::regular(Point::new(2, 2))],
+                speed_effect: None,
+                game_mode: GameMode::Teams,
+                snakes: vec![SnakeState {
+                    owner: SnakeOwner::Team(2),
+                    body: vec![Point::new(2, 1), Point::new(1, 1), Point::new(0, 1)],
+                    direction: Direction::East,
+                    score: 0,
+                    eliminated: None,
+                }],
+                winner: None,
+                seed: 0,
+            },
+        ];
+
+        for game_state in &game_states {
+            let proto_game_state = proto::GameState::from(game_state.clone());
+            assert_eq!(&GameState::try_from(proto_game_state).unwrap(), game_state);
+        }
+
+        // Game states the client can't read are errors rather than panics
+        let unreadable = [
+            proto::GameState {
+                phase: 99,
+                ..proto::GameState::from(game_states[0].clone())
+            },
+            proto::GameState {
+                game_over_reason: 99,
+                ..proto::GameState::from(game_states[0].clone())
+            },
+            proto::GameState {
+                series: None,
+                ..proto::GameState::from(game_states[0].clone())
+            },
+            proto::GameState {
+                food_items: vec![proto::Food::default()],
+                ..proto::GameState::from(game_states[0].clone())
+            },
+            proto::GameState {
+                move_direction: 99,
+                ..proto::GameState::from(game_states[0].clone())
+            },
+            proto::GameState {
+                board_mode: 99,
+                ..proto::GameState::from(game_states[0].clone())
+            },
+            proto::GameState {
+                game_mode: 99,
+                ..proto::GameState::from(game_states[0].clone())
+            },
+        ];
+        let mut unreadable = Vec::from(unreadable);
+        let mut game_state = proto::GameState::from(game_states[0].clone());
+        game_state.snakes[1].elimination_reason = 99;
+        unreadable.push(game_state);
+        let mut game_state = proto::GameState::from(game_states[0].clone());
+        game_state.food_items[1].kind = 99;
+        unreadable.push(game_state);
+        let mut game_state = proto::GameState::from(game_states[0].clone());
+        game_state.speed_effect.as_mut().unwrap().kind = 99;
+        unreadable.push(game_state);
+        for game_state in unreadable {
+            assert!(matches!(
+                GameState::try_from(game_state),
+                Err(GameError::InvalidArgument(_))
+            ));
+        }
+
+        // 1.5 seconds left shows as 2 so that the countdown reads 3, 2, 1
+        let countdown = proto::GameState::from(game_states[1].clone());
+        assert_eq!(countdown.countdown_remaining_seconds, 2);
+        assert_eq!(countdown.first_tick_at_millis, 1_700_000_001_500);
+    }
 }
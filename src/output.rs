@@ -1,7 +1,8 @@
-use crate::GameState;
+use std::collections::{HashMap, HashSet};
+
+use crate::{BoardMode, FoodKind, GameState, Point};
 
 pub fn print_world(game_state: &GameState) {
-    let food = game_state.food;
     let mut sorted_body = game_state.body.clone();
     // sort by row, then by column
     sorted_body.sort_by(|a, b| {
@@ -13,18 +14,91 @@ pub fn print_world(game_state: &GameState) {
     });
 
     let empty_space_separator = "-".to_string();
+    let walls: HashSet<&Point> = game_state.walls.iter().collect();
+    // Rusty passes through the edges of a wrapping board, so they're drawn open
+    let edge = match game_state.board_mode {
+        BoardMode::Bounded => "|",
+        BoardMode::Wrap => ":",
+    };
+
+    // Arena snakes still in play are drawn with a letter each, capitalized at the head
+    let mut snake_cells = HashMap::new();
+    let in_play = game_state
+        .snakes
+        .iter()
+        .filter(|snake| snake.eliminated.is_none());
+    for (letter, snake) in ('a'..='z').cycle().zip(in_play) {
+        for (index, point) in snake.body.iter().enumerate() {
+            let symbol = match index {
+                0 => letter.to_ascii_uppercase(),
+                _ => letter,
+            };
+            snake_cells.insert(*point, symbol);
+        }
+    }
 
     let mut point_inter = sorted_body.iter();
     let mut current_point = point_inter.next();
+    if let Some(head) = game_state.body.first() {
+        println!(
+            "Head Point: {:?} Direction: {:?}",
+            head, game_state.direction
+        );
+    }
+    println!("Phase: {:?}", game_state.phase);
+    println!(
+        "Players: {} ({} active, {} idle), spectators: {}",
+        game_state.num_users, game_state.num_active, game_state.num_idle, game_state.num_spectators
+    );
+    for player in &game_state.roster {
+        let mut tags = Vec::new();
+        if player.is_host {
+            tags.push("host");
+        }
+        if player.is_idle {
+            tags.push("idle");
+        }
+        if player.is_spectator {
+            tags.push("spectator");
+        }
+        let team_tag = player.team.map(|team| format!("team {}", team));
+        if let Some(team_tag) = &team_tag {
+            tags.push(team_tag);
+        }
+        match tags.is_empty() {
+            true => println!("  {}", player.profile.display_name),
+            false => println!("  {} ({})", player.profile.display_name, tags.join(", ")),
+        }
+    }
+    if let Some(countdown_remaining) = game_state.countdown_remaining {
+        println!("Starting in: {:.1}s", countdown_remaining.as_secs_f32());
+    }
     println!(
-        "Head Point: {:?} Direction: {:?}",
-        game_state.body.first().unwrap(),
-        game_state.direction
+        "Round: {} Score: {} Series total: {} Best: {}",
+        game_state.series.round,
+        game_state.score,
+        game_state.series.total_score,
+        game_state.series.best_score
     );
     println!("Game Over Reason: {:?}", game_state.game_over_reason);
+    for snake in &game_state.snakes {
+        match &snake.eliminated {
+            Some(reason) => println!("  {} scored {} ({:?})", snake.owner, snake.score, reason),
+            None => println!("  {} scored {}", snake.owner, snake.score),
+        }
+    }
+    if let Some(winner) = &game_state.winner {
+        println!("Winner: {}", winner);
+    }
     println!("Food: {:?}", game_state.food);
+    if let Some(speed_effect) = game_state.speed_effect {
+        println!(
+            "{:?} for {} more ticks",
+            speed_effect.kind, speed_effect.ticks_left
+        );
+    }
     for row_index in 0..game_state.height {
-        print!("{}\t|", row_index);
+        print!("{}\t{}", row_index, edge);
         // ignore points that are out of bounds.
         // skips points when y < 0 (starting row.)
         // Skips if y >= current row and x < 0
@@ -38,8 +112,18 @@ pub fn print_world(game_state: &GameState) {
         }
         for column_index in 0..game_state.width {
             let mut grid_point_output = empty_space_separator.clone();
-            if food.y == row_index && food.x == column_index {
-                grid_point_output = "*".to_string();
+            if walls.contains(&Point::new(column_index, row_index)) {
+                grid_point_output = "#".to_string();
+            }
+            let food = game_state
+                .food
+                .iter()
+                .find(|food| food.position == Point::new(column_index, row_index));
+            if let Some(food) = food {
+                grid_point_output = food_symbol(food.kind).to_string();
+            }
+            if let Some(symbol) = snake_cells.get(&Point::new(column_index, row_index)) {
+                grid_point_output = symbol.to_string();
             }
             if let Some(point) = current_point {
                 if point.y == row_index && point.x == column_index {
@@ -50,16 +134,29 @@ pub fn print_world(game_state: &GameState) {
             }
             print!("{}", grid_point_output);
         }
-        print!("|\n");
+        println!("{}", edge);
     }
     println!("{:?}", game_state.body);
 }
 
+fn food_symbol(kind: FoodKind) -> &'static str {
+    match kind {
+        FoodKind::Regular => "*",
+        FoodKind::Bonus => "$",
+        FoodKind::Shrink => "%",
+        FoodKind::SpeedUp => ">",
+        FoodKind::SlowDown => "<",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::output::print_world;
     use crate::types::Direction;
-    use crate::{GameOverReason, GameState, Point};
+    use crate::{
+        BoardMode, FoodItem, GameMode, GameOverReason, GamePhase, GameState, Point, SeriesScore,
+        SnakeOwner, SnakeState,
+    };
 
     #[tokio::test]
     async fn output_missing_food() {
@@ -77,10 +174,30 @@ mod tests {
             width: size,
             tick: 1000,
             game_over_reason: Some(GameOverReason::OutOfBounds),
+            phase: GamePhase::Finished,
+            board_mode: BoardMode::Bounded,
+            walls: Vec::new(),
+            countdown_remaining: None,
+            first_tick_at: None,
             direction: Direction::North,
             num_users: 1,
+            num_active: 1,
+            num_idle: 0,
+            num_spectators: 0,
+            score: 0,
+            series: SeriesScore {
+                round: 1,
+                total_score: 0,
+                best_score: 0,
+            },
+            roster: Vec::new(),
             body: test_body,
-            food: Point { x: 0, y: 2 },
+            food: vec![FoodItem::regular(Point { x: 0, y: 2 })],
+            speed_effect: None,
+            game_mode: GameMode::CrowdVote,
+            snakes: Vec::new(),
+            winner: None,
+            seed: 0,
         });
     }
 
@@ -99,10 +216,30 @@ mod tests {
             width: size,
             tick: 1000,
             game_over_reason: Some(GameOverReason::OutOfBounds),
+            phase: GamePhase::Finished,
+            board_mode: BoardMode::Bounded,
+            walls: Vec::new(),
+            countdown_remaining: None,
+            first_tick_at: None,
             direction: Direction::North,
             num_users: 1,
+            num_active: 1,
+            num_idle: 0,
+            num_spectators: 0,
+            score: 0,
+            series: SeriesScore {
+                round: 1,
+                total_score: 0,
+                best_score: 0,
+            },
+            roster: Vec::new(),
             body: test_body,
-            food: Point::new(0, 0),
+            food: vec![FoodItem::regular(Point::new(0, 0))],
+            speed_effect: None,
+            game_mode: GameMode::CrowdVote,
+            snakes: Vec::new(),
+            winner: None,
+            seed: 0,
         });
     }
 
@@ -125,10 +262,30 @@ mod tests {
             width: size,
             tick: 1000,
             game_over_reason: Some(GameOverReason::OutOfBounds),
+            phase: GamePhase::Finished,
+            board_mode: BoardMode::Bounded,
+            walls: Vec::new(),
+            countdown_remaining: None,
+            first_tick_at: None,
             direction: Direction::North,
             num_users: 1,
+            num_active: 1,
+            num_idle: 0,
+            num_spectators: 0,
+            score: 0,
+            series: SeriesScore {
+                round: 1,
+                total_score: 0,
+                best_score: 0,
+            },
+            roster: Vec::new(),
             body: test_body,
-            food: Point::new(0, 0),
+            food: vec![FoodItem::regular(Point::new(0, 0))],
+            speed_effect: None,
+            game_mode: GameMode::CrowdVote,
+            snakes: Vec::new(),
+            winner: None,
+            seed: 0,
         });
     }
 
@@ -148,10 +305,76 @@ mod tests {
             width: size,
             tick: 1000,
             game_over_reason: Some(GameOverReason::OutOfBounds),
+            phase: GamePhase::Finished,
+            board_mode: BoardMode::Bounded,
+            walls: Vec::new(),
+            countdown_remaining: None,
+            first_tick_at: None,
             direction: Direction::North,
             num_users: 1,
+            num_active: 1,
+            num_idle: 0,
+            num_spectators: 0,
+            score: 0,
+            series: SeriesScore {
+                round: 1,
+                total_score: 0,
+                best_score: 0,
+            },
+            roster: Vec::new(),
             body: test_body,
-            food: head,
+            food: vec![FoodItem::regular(head)],
+            speed_effect: None,
+            game_mode: GameMode::CrowdVote,
+            snakes: Vec::new(),
+            winner: None,
+            seed: 0,
+        });
+    }
+
+    #[tokio::test]
+    async fn output_arena() {
+        let size = 8;
+        let snake = |user_id: &str, y, eliminated| SnakeState {
+            owner: SnakeOwner::Player(user_id.to_string()),
+            body: vec![Point::new(2, y), Point::new(1, y), Point::new(0, y)],
+            direction: Direction::East,
+            score: 1,
+            eliminated,
+        };
+
+        print_world(&GameState {
+            height: size,
+            width: size,
+            tick: 10,
+            game_over_reason: Some(GameOverReason::Winner),
+            phase: GamePhase::Finished,
+            board_mode: BoardMode::Bounded,
+            walls: Vec::new(),
+            countdown_remaining: None,
+            first_tick_at: None,
+            direction: Direction::East,
+            num_users: 2,
+            num_active: 2,
+            num_idle: 0,
+            num_spectators: 0,
+            score: 0,
+            series: SeriesScore {
+                round: 1,
+                total_score: 0,
+                best_score: 0,
+            },
+            roster: Vec::new(),
+            body: Vec::new(),
+            food: vec![FoodItem::regular(Point::new(4, 4))],
+            speed_effect: None,
+            game_mode: GameMode::Arena,
+            snakes: vec![
+                snake("amy", 2, None),
+                snake("zed", 5, Some(GameOverReason::HitSnake)),
+            ],
+            winner: Some(SnakeOwner::Player("amy".to_string())),
+            seed: 0,
         });
     }
 }
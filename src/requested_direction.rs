@@ -14,29 +14,37 @@ impl RequestedDirection {
     }
 
     pub async fn add_direction(&mut self, user_id: &str, direction: Direction) {
-        self.directions
-            .insert(user_id.to_string(), direction);
+        self.directions.insert(user_id.to_string(), direction);
+    }
+
+    pub async fn remove_direction(&mut self, user_id: &str) {
+        self.directions.remove(user_id);
     }
 
     pub async fn clear(&mut self) {
         self.directions.clear();
     }
 
+    /// The direction with the most votes. Ties go to the direction that comes first in
+    /// `Direction::ALL`, so the same votes always pick the same direction.
     pub async fn calculate_direction(&self) -> Option<Direction> {
         let mut directions_count: HashMap<Direction, usize> = HashMap::new();
 
-        for (_, direction) in &self.directions {
-            *directions_count.entry(*direction).or_insert(1) += 1;
+        for direction in self.directions.values() {
+            *directions_count.entry(*direction).or_insert(0) += 1;
         }
 
-        directions_count
+        // max_by_key picks the last of equal counts, so the directions are walked in reverse
+        Direction::ALL
             .iter()
-            .max_by(|a, b| a.1.cmp(&b.1))
-            .map(|(k, _v)| k.clone())
-    }
-
-    pub async fn len(&self) -> usize {
-        self.directions.len()
+            .rev()
+            .filter_map(|direction| {
+                directions_count
+                    .get(direction)
+                    .map(|count| (*direction, *count))
+            })
+            .max_by_key(|(_, count)| *count)
+            .map(|(direction, _)| direction)
     }
 }
 
@@ -124,6 +132,30 @@ mod tests {
         assert!(matches!(Some(Direction::South), _max_direction));
     }
 
+    #[tokio::test]
+    async fn tie_is_broken_in_a_fixed_order() {
+        let mut requested_direction = RequestedDirection::new();
+        for (user_id, direction) in [
+            ("user_west", Direction::West),
+            ("user_south", Direction::South),
+            ("user_east", Direction::East),
+        ] {
+            requested_direction.add_direction(user_id, direction).await;
+        }
+        assert_eq!(
+            requested_direction.calculate_direction().await,
+            Some(Direction::East)
+        );
+
+        requested_direction
+            .add_direction("user_north", Direction::North)
+            .await;
+        assert_eq!(
+            requested_direction.calculate_direction().await,
+            Some(Direction::North)
+        );
+    }
+
     fn generate_user_ids(num_users: usize) -> Vec<String> {
         let mut users: Vec<String> = Vec::new();
         for i in 0..num_users {
@@ -1,17 +1,39 @@
+use std::pin::Pin;
+use std::sync::Arc;
+
 use crate::{
     proto::{
-        rusty_server::Rusty, CreateReply, CreateRequest, GameStatusReply, GameStatusRequest,
-        JoinReply, JoinRequest, StartReply, StartRequest, UpdateReply, UpdateRequest,
+        self, play_request::Action, rusty_server::Rusty, ConfigureReply, ConfigureRequest,
+        CreateReply, CreateRequest, DeleteGameReply, DeleteGameRequest, ErrorDetails, ErrorReason,
+        GameStatusReply, GameStatusRequest, JoinReply, JoinRequest, KickReply, KickRequest,
+        LeaveReply, LeaveRequest, ListGamesReply, ListGamesRequest, PauseReply, PauseRequest,
+        PlayReply, PlayRequest, RestartReply, RestartRequest, ResumeReply, ResumeRequest,
+        StartReply, StartRequest, TransferHostReply, TransferHostRequest, UpdateReply,
+        UpdateRequest, WatchGameReply, WatchGameRequest,
     },
+    read_proto_enum,
     types::Direction,
-    GameError, GameState, JoinGameReply, RustyGame,
+    vote_limiter::VoteLimiter,
+    CreateGameReply, DirectionVoter, GameError, GameListPage, GameSettings, GameState,
+    JoinGameReply, PlaySession, Principal, RustyGame, ServerConfig, SessionInterceptor,
+};
+use log::{debug, info, warn};
+use prost::Message;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream, ReceiverStream},
+    Stream, StreamExt,
 };
-use log::{debug, info};
-use tonic::{Code, Request, Response, Status};
+use tonic::{Code, Request, Response, Status, Streaming};
+
+type WatchGameStream = Pin<Box<dyn Stream<Item = Result<WatchGameReply, Status>> + Send>>;
+type PlayStream = Pin<Box<dyn Stream<Item = Result<PlayReply, Status>> + Send>>;
 
 #[derive(Default)]
 pub struct RustyService {
     rusty_game: RustyGame,
+    /// Shared with the Play streams, which count votes against the same limit as Update
+    vote_limiter: Arc<VoteLimiter>,
 }
 
 #[tonic::async_trait]
@@ -22,12 +44,62 @@ impl Rusty for RustyService {
     ) -> Result<Response<CreateReply>, Status> {
         info!("Received Create request from {:?}", request.remote_addr());
 
-        let game_id = &self.create_game_internal(request.into_inner()).await;
+        match self.create_game_internal(request.into_inner()).await {
+            Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
+            Ok(reply) => {
+                let reply = CreateReply {
+                    game_id: reply.game_id,
+                    host_user_id: reply.host_user_id,
+                    session_token: reply.session_token,
+                };
+                Ok(Response::new(reply))
+            }
+        }
+    }
 
-        let reply = CreateReply {
-            game_id: game_id.into(),
-        };
-        Ok(Response::new(reply))
+    async fn configure(
+        &self,
+        request: Request<ConfigureRequest>,
+    ) -> Result<Response<ConfigureReply>, Status> {
+        info!(
+            "Received configure request from {:?}",
+            request.remote_addr()
+        );
+        match self.configure_game_internal(request).await {
+            Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
+            Ok(_) => {
+                let reply = ConfigureReply {};
+                Ok(Response::new(reply))
+            }
+        }
+    }
+
+    async fn kick(&self, request: Request<KickRequest>) -> Result<Response<KickReply>, Status> {
+        info!("Received kick request from {:?}", request.remote_addr());
+        match self.kick_player_internal(request).await {
+            Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
+            Ok(_) => {
+                let reply = KickReply {};
+                Ok(Response::new(reply))
+            }
+        }
+    }
+
+    async fn transfer_host(
+        &self,
+        request: Request<TransferHostRequest>,
+    ) -> Result<Response<TransferHostReply>, Status> {
+        info!(
+            "Received transfer host request from {:?}",
+            request.remote_addr()
+        );
+        match self.transfer_host_internal(request).await {
+            Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
+            Ok(_) => {
+                let reply = TransferHostReply {};
+                Ok(Response::new(reply))
+            }
+        }
     }
 
     async fn update(
@@ -35,7 +107,7 @@ impl Rusty for RustyService {
         request: Request<UpdateRequest>,
     ) -> Result<Response<UpdateReply>, Status> {
         debug!("Received update request from {:?}", request.remote_addr());
-        match self.update_game_internal(request.into_inner()).await {
+        match self.update_game_internal(request).await {
             Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
             Ok(game_state) => {
                 let reply = UpdateReply {
@@ -55,15 +127,27 @@ impl Rusty for RustyService {
                     user_id: reply.user_id,
                     width: reply.width as u32,
                     height: reply.height as u32,
+                    session_token: reply.session_token,
                 };
                 Ok(Response::new(reply))
             }
         }
     }
 
+    async fn leave(&self, request: Request<LeaveRequest>) -> Result<Response<LeaveReply>, Status> {
+        info!("Received leave request from {:?}", request.remote_addr());
+        match self.leave_game_internal(request).await {
+            Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
+            Ok(_) => {
+                let reply = LeaveReply {};
+                Ok(Response::new(reply))
+            }
+        }
+    }
+
     async fn start(&self, request: Request<StartRequest>) -> Result<Response<StartReply>, Status> {
         info!("Recieved start request from {:?}", request.remote_addr());
-        match self.start_game_internal(request.into_inner()).await {
+        match self.start_game_internal(request).await {
             Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
             Ok(_) => {
                 let reply = StartReply {};
@@ -72,12 +156,51 @@ impl Rusty for RustyService {
         }
     }
 
+    async fn restart(
+        &self,
+        request: Request<RestartRequest>,
+    ) -> Result<Response<RestartReply>, Status> {
+        info!("Received restart request from {:?}", request.remote_addr());
+        match self.restart_game_internal(request).await {
+            Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
+            Ok(_) => {
+                let reply = RestartReply {};
+                Ok(Response::new(reply))
+            }
+        }
+    }
+
+    async fn pause(&self, request: Request<PauseRequest>) -> Result<Response<PauseReply>, Status> {
+        info!("Received pause request from {:?}", request.remote_addr());
+        match self.pause_game_internal(request).await {
+            Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
+            Ok(_) => {
+                let reply = PauseReply {};
+                Ok(Response::new(reply))
+            }
+        }
+    }
+
+    async fn resume(
+        &self,
+        request: Request<ResumeRequest>,
+    ) -> Result<Response<ResumeReply>, Status> {
+        info!("Received resume request from {:?}", request.remote_addr());
+        match self.resume_game_internal(request).await {
+            Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
+            Ok(_) => {
+                let reply = ResumeReply {};
+                Ok(Response::new(reply))
+            }
+        }
+    }
+
     async fn game_status(
         &self,
         request: Request<GameStatusRequest>,
     ) -> Result<Response<GameStatusReply>, Status> {
         debug!("Received status request from {:?}", request.remote_addr());
-        match self.game_status_internal(request.into_inner()).await {
+        match self.game_status_internal(request).await {
             Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
             Ok(game_state) => {
                 let reply = GameStatusReply {
@@ -87,62 +210,509 @@ impl Rusty for RustyService {
             }
         }
     }
+
+    type WatchGameStream = WatchGameStream;
+
+    async fn watch_game(
+        &self,
+        request: Request<WatchGameRequest>,
+    ) -> Result<Response<Self::WatchGameStream>, Status> {
+        info!("Received watch request from {:?}", request.remote_addr());
+        match self.watch_game_internal(request).await {
+            Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
+            Ok(receiver) => Ok(Response::new(Self::into_watch_game_stream(receiver))),
+        }
+    }
+
+    async fn list_games(
+        &self,
+        request: Request<ListGamesRequest>,
+    ) -> Result<Response<ListGamesReply>, Status> {
+        debug!(
+            "Received list games request from {:?}",
+            request.remote_addr()
+        );
+        match self.list_games_internal(request.into_inner()).await {
+            Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
+            Ok(page) => {
+                let reply = ListGamesReply {
+                    games: page.games.into_iter().map(|g| g.into()).collect(),
+                    next_page_token: page.next_page_token.unwrap_or_default(),
+                };
+                Ok(Response::new(reply))
+            }
+        }
+    }
+
+    async fn delete_game(
+        &self,
+        request: Request<DeleteGameRequest>,
+    ) -> Result<Response<DeleteGameReply>, Status> {
+        info!("Received delete request from {:?}", request.remote_addr());
+        match self.delete_game_internal(request).await {
+            Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
+            Ok(_) => {
+                let reply = DeleteGameReply {};
+                Ok(Response::new(reply))
+            }
+        }
+    }
+
+    type PlayStream = PlayStream;
+
+    async fn play(
+        &self,
+        request: Request<Streaming<PlayRequest>>,
+    ) -> Result<Response<Self::PlayStream>, Status> {
+        info!("Received play request from {:?}", request.remote_addr());
+        let principal = request.extensions().get::<Principal>().cloned();
+        let mut inbound = request.into_inner();
+
+        let session = match inbound.message().await? {
+            Some(PlayRequest {
+                action: Some(Action::Session(session)),
+            }) => session,
+            _ => {
+                return Err(Status::new(
+                    Code::InvalidArgument,
+                    "The first Play message must contain the session.",
+                ))
+            }
+        };
+
+        let principal = match principal {
+            Some(principal) if principal.game_id() == session.game_id => principal,
+            _ => {
+                return Err(Self::convert_game_error_to_status(
+                    &GameError::Unauthenticated,
+                ))
+            }
+        };
+        match self.rusty_game.play_game(principal).await {
+            Err(game_error) => Err(Self::convert_game_error_to_status(&game_error)),
+            Ok(play_session) => Ok(Response::new(Self::into_play_stream(
+                play_session,
+                inbound,
+                self.vote_limiter.clone(),
+            ))),
+        }
+    }
 }
 
 impl RustyService {
     pub fn new() -> Self {
+        Self::with_config(ServerConfig::default())
+    }
+
+    pub fn with_config(config: ServerConfig) -> Self {
         env_logger::init();
         Self {
-            rusty_game: RustyGame::default(),
+            rusty_game: RustyGame::with_config(config),
+            vote_limiter: Arc::default(),
+        }
+    }
+
+    /// Must wrap the service so that requests carry the principal of their session token
+    pub fn session_interceptor(&self) -> SessionInterceptor {
+        self.rusty_game.session_interceptor()
+    }
+
+    /// The user the session interceptor authenticated, who must have joined the requested
+    /// game
+    fn principal<T>(request: &Request<T>, game_id: &str) -> Result<Principal, GameError> {
+        match request.extensions().get::<Principal>() {
+            Some(principal) if principal.game_id() == game_id => Ok(principal.clone()),
+            _ => Err(GameError::Unauthenticated),
         }
     }
 
-    async fn create_game_internal(&self, request: CreateRequest) -> String {
+    async fn create_game_internal(
+        &self,
+        request: CreateRequest,
+    ) -> Result<CreateGameReply, GameError> {
+        let settings = GameSettings::try_from(&request)?;
+        let host_profile = request.host_profile.unwrap_or_default().into();
+        self.rusty_game.create_game(settings, host_profile).await
+    }
+
+    async fn configure_game_internal(
+        &self,
+        request: Request<ConfigureRequest>,
+    ) -> Result<(), GameError> {
+        let principal = Self::principal(&request, &request.get_ref().game_id)?;
+        let request = request.into_inner();
+        let settings = GameSettings::try_from(&request)?;
+        self.rusty_game.configure_game(principal, settings).await
+    }
+
+    async fn kick_player_internal(&self, request: Request<KickRequest>) -> Result<(), GameError> {
+        let principal = Self::principal(&request, &request.get_ref().game_id)?;
+        let request = request.into_inner();
         self.rusty_game
-            .create_game(
-                request.width as i32,
-                request.height as i32,
-                request.tick_duration_millis as u64,
-            )
+            .kick_player(principal, request.player_id)
             .await
     }
 
-    async fn update_game_internal(&self, request: UpdateRequest) -> Result<GameState, GameError> {
-        let direction: Direction = request.move_direction.into();
+    async fn transfer_host_internal(
+        &self,
+        request: Request<TransferHostRequest>,
+    ) -> Result<(), GameError> {
+        let principal = Self::principal(&request, &request.get_ref().game_id)?;
+        let request = request.into_inner();
         self.rusty_game
-            .update_game(request.game_id, request.user_id, direction)
+            .transfer_host(principal, request.new_host_id)
             .await
     }
 
+    async fn update_game_internal(
+        &self,
+        request: Request<UpdateRequest>,
+    ) -> Result<GameState, GameError> {
+        let principal = Self::principal(&request, &request.get_ref().game_id)?;
+        let request = request.into_inner();
+        let direction = Direction::try_from(request.move_direction)?;
+        self.vote_limiter.check(&principal)?;
+        self.rusty_game.update_game(principal, direction).await
+    }
+
     async fn game_status_internal(
         &self,
-        request: GameStatusRequest,
+        request: Request<GameStatusRequest>,
     ) -> Result<GameState, GameError> {
+        let principal = Self::principal(&request, &request.get_ref().game_id)?;
+        self.rusty_game.game_status(principal).await
+    }
+
+    async fn join_game_internal(&self, request: JoinRequest) -> Result<JoinGameReply, GameError> {
+        let profile = request.profile.unwrap_or_default().into();
+        let mode = read_proto_enum(request.mode, proto::JoinMode::from_i32, "join mode")?;
         self.rusty_game
-            .game_status(request.game_id, request.user_id)
+            .join_game(
+                request.game_id,
+                profile,
+                mode.into(),
+                Some(request.team).filter(|team| *team > 0),
+            )
             .await
     }
 
-    async fn join_game_internal(&self, request: JoinRequest) -> Result<JoinGameReply, GameError> {
-        self.rusty_game.join_game(request.game_id).await
+    async fn leave_game_internal(&self, request: Request<LeaveRequest>) -> Result<(), GameError> {
+        let principal = Self::principal(&request, &request.get_ref().game_id)?;
+        self.rusty_game.leave_game(principal).await
+    }
+
+    async fn start_game_internal(&self, request: Request<StartRequest>) -> Result<(), GameError> {
+        let principal = Self::principal(&request, &request.get_ref().game_id)?;
+        self.rusty_game.start_game(principal).await
+    }
+
+    async fn restart_game_internal(
+        &self,
+        request: Request<RestartRequest>,
+    ) -> Result<(), GameError> {
+        let principal = Self::principal(&request, &request.get_ref().game_id)?;
+        self.rusty_game.restart_game(principal).await
+    }
+
+    async fn pause_game_internal(&self, request: Request<PauseRequest>) -> Result<(), GameError> {
+        let principal = Self::principal(&request, &request.get_ref().game_id)?;
+        self.rusty_game.pause_game(principal).await
+    }
+
+    async fn resume_game_internal(&self, request: Request<ResumeRequest>) -> Result<(), GameError> {
+        let principal = Self::principal(&request, &request.get_ref().game_id)?;
+        self.rusty_game.resume_game(principal).await
     }
 
-    async fn start_game_internal(&self, request: StartRequest) -> Result<(), GameError> {
+    async fn delete_game_internal(
+        &self,
+        request: Request<DeleteGameRequest>,
+    ) -> Result<(), GameError> {
+        let principal = Self::principal(&request, &request.get_ref().game_id)?;
+        self.rusty_game.delete_game(principal).await
+    }
+
+    async fn list_games_internal(
+        &self,
+        request: ListGamesRequest,
+    ) -> Result<GameListPage, GameError> {
+        let filter = proto::GameFilter::from_i32(request.filter)
+            .ok_or_else(|| GameError::InvalidArgument("Unknown game filter".to_string()))?;
+        let page_token = match request.page_token.is_empty() {
+            true => None,
+            false => Some(request.page_token),
+        };
         self.rusty_game
-            .start_game(request.game_id, request.user_id)
+            .list_games(filter.into(), request.page_size as usize, page_token)
             .await
     }
 
+    async fn watch_game_internal(
+        &self,
+        request: Request<WatchGameRequest>,
+    ) -> Result<broadcast::Receiver<GameState>, GameError> {
+        let principal = Self::principal(&request, &request.get_ref().game_id)?;
+        self.rusty_game.watch_game(principal).await
+    }
+
+    /// Converts game states into watch replies. The stream ends once the game is over.
+    fn into_watch_game_stream(receiver: broadcast::Receiver<GameState>) -> WatchGameStream {
+        let stream = Self::game_state_stream(receiver)
+            .map(|game_state| WatchGameReply {
+                game_state: Some(game_state),
+            })
+            .map(Ok);
+        Box::pin(stream)
+    }
+
+    /// Forwards the player's votes to the game while streaming back the game states.
+    /// A rate limited vote is dropped and answered with a reply saying when to vote again.
+    /// Any other rejected vote ends the stream with the error. The stream also ends once the
+    /// game is over, and stops forwarding votes when the player drops it.
+    fn into_play_stream<S>(
+        play_session: PlaySession,
+        mut inbound: S,
+        vote_limiter: Arc<VoteLimiter>,
+    ) -> PlayStream
+    where
+        S: Stream<Item = Result<PlayRequest, Status>> + Send + Unpin + 'static,
+    {
+        let PlaySession { game_states, voter } = play_session;
+        let (notice_sender, mut notices) = mpsc::channel::<Result<PlayReply, Status>>(1);
+
+        let inbound_task = tokio::spawn(async move {
+            while let Some(request) = inbound.next().await {
+                let status = match request {
+                    Ok(PlayRequest {
+                        action: Some(Action::MoveDirection(move_direction)),
+                    }) => match Self::vote(&voter, &vote_limiter, move_direction).await {
+                        Ok(_) => continue,
+                        // The player can keep playing once their vote window ends
+                        Err(GameError::RateLimited { retry_after }) => {
+                            let notice = PlayReply {
+                                game_state: None,
+                                vote_retry_after_millis: retry_after.as_millis() as u64,
+                            };
+                            let _ = notice_sender.send(Ok(notice)).await;
+                            continue;
+                        }
+                        Err(game_error) => Self::convert_game_error_to_status(&game_error),
+                    },
+                    Ok(_) => Status::new(
+                        Code::InvalidArgument,
+                        "Only directions can be sent after the session.",
+                    ),
+                    Err(status) => status,
+                };
+                let _ = notice_sender.send(Err(status)).await;
+                break;
+            }
+        });
+
+        let (reply_sender, replies) = mpsc::channel(1);
+        let mut game_states = Box::pin(Self::game_state_stream(game_states));
+        tokio::spawn(async move {
+            loop {
+                let reply = tokio::select! {
+                    game_state = game_states.next() => match game_state {
+                        Some(game_state) => Ok(PlayReply {
+                            game_state: Some(game_state),
+                            vote_retry_after_millis: 0,
+                        }),
+                        // The game is over
+                        None => break,
+                    },
+                    Some(notice) = notices.recv() => notice,
+                    _ = reply_sender.closed() => break,
+                };
+                let is_error = reply.is_err();
+                if reply_sender.send(reply).await.is_err() || is_error {
+                    break;
+                }
+            }
+            inbound_task.abort();
+        });
+        Box::pin(ReceiverStream::new(replies))
+    }
+
+    async fn vote(
+        voter: &DirectionVoter,
+        vote_limiter: &VoteLimiter,
+        move_direction: i32,
+    ) -> Result<GameState, GameError> {
+        let direction = Direction::try_from(move_direction)?;
+        vote_limiter.check(voter.principal())?;
+        voter.vote(direction).await
+    }
+
+    /// Converts game states into proto game states. A watcher that fell behind skips the
+    /// states it missed and carries on from the oldest one still buffered.
+    fn game_state_stream(
+        receiver: broadcast::Receiver<GameState>,
+    ) -> impl Stream<Item = proto::GameState> {
+        BroadcastStream::new(receiver).filter_map(|game_state| match game_state {
+            Ok(game_state) => Some(game_state.into()),
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                warn!("Watcher fell behind and skipped {} ticks", skipped);
+                None
+            }
+        })
+    }
+
+    /// Maps the error to a status code, with the error's fields in `ErrorDetails`
     fn convert_game_error_to_status(error: &GameError) -> Status {
-        match error {
-            GameError::InvalidGame => Status::new(
-                Code::InvalidArgument,
-                "Invalid Game ID. Create a game first.",
-            ),
-            GameError::InvalidUser => {
-                Status::new(Code::InvalidArgument, "Invalid User ID. Join a game first.")
+        let mut details = ErrorDetails::default();
+        let (code, reason) = match error {
+            GameError::GameNotFound(game_id) => {
+                details.game_id = game_id.clone();
+                (Code::NotFound, ErrorReason::GameNotFound)
+            }
+            GameError::UserNotFound(user_id) => {
+                details.user_id = user_id.clone();
+                (Code::NotFound, ErrorReason::UserNotFound)
+            }
+            GameError::AlreadyStarted => (Code::FailedPrecondition, ErrorReason::AlreadyStarted),
+            GameError::NotStarted => (Code::FailedPrecondition, ErrorReason::NotStarted),
+            GameError::GameOver => (Code::FailedPrecondition, ErrorReason::GameOver),
+            GameError::InvalidTransition { from, to } => {
+                details.from_phase = proto::GamePhase::from(*from).into();
+                details.to_phase = proto::GamePhase::from(*to).into();
+                (Code::FailedPrecondition, ErrorReason::InvalidTransition)
+            }
+            GameError::InvalidArgument(_) => (Code::InvalidArgument, ErrorReason::InvalidArgument),
+            GameError::Unauthenticated => (Code::Unauthenticated, ErrorReason::Unauthenticated),
+            GameError::PermissionDenied => (Code::PermissionDenied, ErrorReason::PermissionDenied),
+            GameError::NameTaken(display_name) => {
+                details.display_name = display_name.clone();
+                (Code::AlreadyExists, ErrorReason::NameTaken)
+            }
+            GameError::Spectating => (Code::PermissionDenied, ErrorReason::Spectating),
+            GameError::Full { max_games } => {
+                details.max_games = *max_games as u32;
+                (Code::ResourceExhausted, ErrorReason::Full)
+            }
+            GameError::RateLimited { retry_after } => {
+                details.retry_after_millis = retry_after.as_millis() as u64;
+                (Code::ResourceExhausted, ErrorReason::RateLimited)
             }
-            GameError::Internal => Status::new(Code::Internal, "Internal error"),
+            GameError::Internal(_) => (Code::Internal, ErrorReason::Internal),
+        };
+        details.reason = reason.into();
+
+        Status::with_details(code, error.to_string(), details.encode_to_vec().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use prost::Message;
+    use tonic::Code;
+
+    use tokio::{sync::mpsc, time::timeout};
+    use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+
+    use crate::{
+        proto::{play_request::Action, ErrorDetails, ErrorReason, PlayRequest},
+        service::{PlayStream, RustyService},
+        GameError, GameSettings, PlayerProfile, Principal, RustyGame,
+    };
+
+    type PlayRequests = mpsc::Sender<Result<PlayRequest, tonic::Status>>;
+
+    /// A host playing a new game, with the sender of their votes and the stream of replies
+    async fn play() -> (RustyGame, Principal, PlayRequests, PlayStream) {
+        let rusty_game = RustyGame::default();
+        let reply = rusty_game
+            .create_game(GameSettings::default(), PlayerProfile::new("Host"))
+            .await
+            .unwrap();
+        let host = Principal::new(reply.game_id, reply.host_user_id);
+        let play_session = rusty_game.play_game(host.clone()).await.unwrap();
+        let (requests, inbound) = mpsc::channel(1);
+        let replies = RustyService::into_play_stream(
+            play_session,
+            ReceiverStream::new(inbound),
+            Arc::default(),
+        );
+        (rusty_game, host, requests, replies)
+    }
+
+    fn move_direction(move_direction: i32) -> PlayRequest {
+        PlayRequest {
+            action: Some(Action::MoveDirection(move_direction)),
+        }
+    }
+
+    #[tokio::test]
+    async fn play_stream_rejects_unknown_direction() {
+        let (_rusty_game, _host, requests, mut replies) = play().await;
+        requests.send(Ok(move_direction(99))).await.unwrap();
+        let status = replies.next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(replies.next().await.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn play_stream_reports_rate_limited_votes() {
+        let (_rusty_game, _host, requests, mut replies) = play().await;
+        for _ in 0..10 {
+            requests.send(Ok(move_direction(0))).await.unwrap();
         }
+        tokio::time::advance(Duration::from_millis(400)).await;
+        requests.send(Ok(move_direction(0))).await.unwrap();
+        let reply = replies.next().await.unwrap().unwrap();
+        assert_eq!(reply.game_state, None);
+        assert_eq!(reply.vote_retry_after_millis, 600);
+
+        // The stream stays open
+        tokio::time::advance(Duration::from_millis(600)).await;
+        requests.send(Ok(move_direction(99))).await.unwrap();
+        let status = replies.next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn play_stream_ends_with_the_game() {
+        let (rusty_game, host, requests, mut replies) = play().await;
+        requests.send(Ok(move_direction(0))).await.unwrap();
+        rusty_game.delete_game(host).await.unwrap();
+        // The player's upstream is still open
+        let end = timeout(Duration::from_secs(1), replies.next()).await;
+        assert!(matches!(end, Ok(None)));
+        timeout(Duration::from_secs(1), requests.closed())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn dropping_play_stream_stops_forwarding_votes() {
+        let (_rusty_game, _host, requests, replies) = play().await;
+        drop(replies);
+        timeout(Duration::from_secs(1), requests.closed())
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn game_error_status_details() {
+        let error = GameError::RateLimited {
+            retry_after: Duration::from_millis(250),
+        };
+        let status = RustyService::convert_game_error_to_status(&error);
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(status.message(), "Too many requests. Try again in 250ms.");
+        let details = ErrorDetails::decode(status.details()).unwrap();
+        assert_eq!(details.reason, ErrorReason::RateLimited as i32);
+        assert_eq!(details.retry_after_millis, 250);
+
+        let error = GameError::GameNotFound("missing".to_string());
+        let status = RustyService::convert_game_error_to_status(&error);
+        assert_eq!(status.code(), Code::NotFound);
+        let details = ErrorDetails::decode(status.details()).unwrap();
+        assert_eq!(details.reason, ErrorReason::GameNotFound as i32);
+        assert_eq!(details.game_id, "missing");
     }
 }
@@ -0,0 +1,181 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::{Arc, RwLock},
+};
+
+use rand::Rng;
+use tonic::{service::Interceptor, Request, Status};
+
+/// Metadata key and header that carry the session token, as `Bearer <token>`
+pub const AUTHORIZATION: &str = "authorization";
+
+/// Random bytes in a session token
+const TOKEN_BYTES: usize = 32;
+
+/// A user whose session token was verified. Only the session store creates principals, so
+/// holding one proves the user joined the game.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Principal {
+    game_id: String,
+    user_id: String,
+}
+
+impl Principal {
+    pub(crate) fn new(game_id: String, user_id: String) -> Self {
+        Self { game_id, user_id }
+    }
+
+    pub fn game_id(&self) -> &str {
+        &self.game_id
+    }
+
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    pub(crate) fn into_user_id(self) -> String {
+        self.user_id
+    }
+}
+
+/// Opaque session tokens issued on join, mapped to the user they were issued to
+#[derive(Default)]
+pub(crate) struct SessionStore {
+    sessions: RwLock<HashMap<String, Principal>>,
+}
+
+impl SessionStore {
+    pub(crate) fn issue(&self, principal: Principal) -> String {
+        let mut token = String::with_capacity(TOKEN_BYTES * 2);
+        for byte in rand::thread_rng().gen::<[u8; TOKEN_BYTES]>() {
+            let _ = write!(token, "{:02x}", byte);
+        }
+        self.sessions
+            .write()
+            .unwrap()
+            .insert(token.clone(), principal);
+        token
+    }
+
+    pub(crate) fn verify(&self, token: &str) -> Option<Principal> {
+        self.sessions.read().unwrap().get(token).cloned()
+    }
+
+    /// Ends the sessions of a user that left or was kicked
+    pub(crate) fn revoke(&self, principal: &Principal) {
+        self.sessions
+            .write()
+            .unwrap()
+            .retain(|_, session| session != principal);
+    }
+
+    /// Ends the sessions of every user of a removed game
+    pub(crate) fn revoke_game(&self, game_id: &str) {
+        self.sessions
+            .write()
+            .unwrap()
+            .retain(|_, session| session.game_id != game_id);
+    }
+}
+
+/// Reads the bearer token from an `authorization` value
+pub fn parse_bearer_token(value: &str) -> Option<&str> {
+    value
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| is_well_formed_token(token))
+}
+
+/// Whether the token looks like one the session store issues, which is checked before
+/// looking it up
+pub fn is_well_formed_token(token: &str) -> bool {
+    token.len() == TOKEN_BYTES * 2 && token.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Verifies the session token of every gRPC request that has one and attaches the
+/// `Principal` to the request extensions. Requests without a token, like `Create` and
+/// `Join`, pass through and are rejected by the calls that need a principal.
+#[derive(Clone)]
+pub struct SessionInterceptor {
+    sessions: Arc<SessionStore>,
+}
+
+impl SessionInterceptor {
+    pub(crate) fn new(sessions: Arc<SessionStore>) -> Self {
+        Self { sessions }
+    }
+}
+
+impl Interceptor for SessionInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let value = match request.metadata().get(AUTHORIZATION) {
+            None => return Ok(request),
+            Some(value) => value
+                .to_str()
+                .map_err(|_| Status::unauthenticated("Malformed authorization metadata"))?,
+        };
+        let principal = parse_bearer_token(value)
+            .and_then(|token| self.sessions.verify(token))
+            .ok_or_else(|| Status::unauthenticated("Invalid or expired session token"))?;
+        request.extensions_mut().insert(principal);
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tonic::{service::Interceptor, Code, Request};
+
+    use crate::session::{
+        is_well_formed_token, parse_bearer_token, Principal, SessionInterceptor, SessionStore,
+        AUTHORIZATION,
+    };
+
+    #[test]
+    fn issue_verify_revoke() {
+        let sessions = SessionStore::default();
+        let principal = Principal::new("game".to_string(), "user".to_string());
+        let token = sessions.issue(principal.clone());
+        let other_token = sessions.issue(Principal::new("other".to_string(), "user".to_string()));
+
+        let header = format!("Bearer {}", token);
+        assert_eq!(parse_bearer_token(&header), Some(token.as_str()));
+        assert_eq!(parse_bearer_token(&token), None);
+        assert!(is_well_formed_token(&token));
+        assert!(!is_well_formed_token(&token[1..]));
+        assert!(!is_well_formed_token(&format!("{}g", &token[1..])));
+        assert_eq!(sessions.verify(&token), Some(principal.clone()));
+
+        sessions.revoke(&principal);
+        assert_eq!(sessions.verify(&token), None);
+        sessions.revoke_game("other");
+        assert_eq!(sessions.verify(&other_token), None);
+    }
+
+    #[test]
+    fn interceptor_attaches_principal() {
+        let sessions = Arc::new(SessionStore::default());
+        let principal = Principal::new("game".to_string(), "user".to_string());
+        let token = sessions.issue(principal.clone());
+        let mut interceptor = SessionInterceptor::new(sessions);
+
+        let mut request = Request::new(());
+        let value = format!("Bearer {}", token).parse().unwrap();
+        request.metadata_mut().insert(AUTHORIZATION, value);
+        let request = interceptor.call(request).unwrap();
+        assert_eq!(request.extensions().get::<Principal>(), Some(&principal));
+
+        // Calls like Join don't need a session
+        let request = interceptor.call(Request::new(())).unwrap();
+        assert_eq!(request.extensions().get::<Principal>(), None);
+
+        let mut request = Request::new(());
+        let value = format!("Bearer {}", "0".repeat(64)).parse().unwrap();
+        request.metadata_mut().insert(AUTHORIZATION, value);
+        let status = interceptor.call(request).unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+    }
+}
@@ -0,0 +1,100 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::{session::Principal, GameError};
+
+/// Votes a user can send within a vote window before they are rate limited
+const MAX_VOTES_PER_WINDOW: u32 = 10;
+const VOTE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Counts a user's votes since the start of their current vote window
+struct VoteWindow {
+    started_at: Instant,
+    votes: u32,
+}
+
+#[derive(Default)]
+struct VoteWindows {
+    windows: HashMap<Principal, VoteWindow>,
+    pruned_at: Option<Instant>,
+}
+
+/// Limits how fast each user can vote, counting the votes of all their Update calls and Play
+/// streams together
+#[derive(Default)]
+pub(crate) struct VoteLimiter {
+    windows: Mutex<VoteWindows>,
+}
+
+impl VoteLimiter {
+    /// Counts the user's vote, unless they have used up the votes of their vote window
+    pub(crate) fn check(&self, principal: &Principal) -> Result<(), GameError> {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        // Windows that have ended are dropped once per window, so users that left are
+        // forgotten
+        let prune = match windows.pruned_at {
+            Some(pruned_at) => now >= pruned_at + VOTE_WINDOW,
+            None => true,
+        };
+        if prune {
+            windows
+                .windows
+                .retain(|_, window| now < window.started_at + VOTE_WINDOW);
+            windows.pruned_at = Some(now);
+        }
+
+        let window = windows
+            .windows
+            .entry(principal.clone())
+            .or_insert(VoteWindow {
+                started_at: now,
+                votes: 0,
+            });
+        let window_ends_at = window.started_at + VOTE_WINDOW;
+        if now >= window_ends_at {
+            window.started_at = now;
+            window.votes = 0;
+        } else if window.votes >= MAX_VOTES_PER_WINDOW {
+            return Err(GameError::RateLimited {
+                retry_after: window_ends_at - now,
+            });
+        }
+        window.votes += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::session::Principal;
+    use crate::vote_limiter::{VoteLimiter, MAX_VOTES_PER_WINDOW, VOTE_WINDOW};
+    use crate::GameError;
+
+    #[tokio::test(start_paused = true)]
+    async fn votes_are_rate_limited() {
+        let limiter = VoteLimiter::default();
+        let user = Principal::new("game".to_string(), "user".to_string());
+        let other = Principal::new("game".to_string(), "other".to_string());
+        for _ in 0..MAX_VOTES_PER_WINDOW {
+            limiter.check(&user).unwrap();
+        }
+
+        tokio::time::advance(Duration::from_millis(400)).await;
+        let Err(GameError::RateLimited { retry_after }) = limiter.check(&user) else {
+            panic!("Expected the vote to be rate limited");
+        };
+        assert_eq!(retry_after, Duration::from_millis(600));
+        assert!(limiter.check(&other).is_ok());
+
+        tokio::time::advance(retry_after).await;
+        assert!(limiter.check(&user).is_ok());
+        // The other user's window ended and was dropped
+        tokio::time::advance(VOTE_WINDOW).await;
+        assert!(limiter.check(&user).is_ok());
+        assert_eq!(limiter.windows.lock().unwrap().windows.len(), 1);
+    }
+}